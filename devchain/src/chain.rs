@@ -0,0 +1,447 @@
+use ethers::{
+    types::{
+        Address, Block, BlockNumber, Bytes, Filter, FilterBlockOption, Log, Signature, Transaction,
+        TransactionReceipt, ValueOrArray, H256, U256, U64,
+    },
+    utils::keccak256,
+};
+use revm::{
+    db::{CacheDB, EmptyDB},
+    primitives::{
+        AccountInfo, BlockEnv, ExecutionResult, Output, SpecId, TransactTo, B256, U256 as EvmU256,
+    },
+    Evm,
+};
+use std::collections::HashMap;
+
+use crate::{DevChainError, BLOCK_TIME, DEV_CHAIN_ID};
+
+pub(crate) const BASE_FEE: u64 = 1_000_000_000;
+pub(crate) const GAS_LIMIT: u64 = 30_000_000;
+const DEV_BALANCE: u128 = 10_000 * 1_000_000_000_000_000_000;
+
+/// A transaction as sent to `eth_call`, `eth_estimateGas` and `eth_sendTransaction`
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CallRequest {
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub gas: Option<U256>,
+    pub gas_price: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub value: Option<U256>,
+    #[serde(alias = "input")]
+    pub data: Option<Bytes>,
+    pub nonce: Option<U256>,
+}
+
+impl CallRequest {
+    /// the price paid per gas in a block with `base_fee`
+    fn effective_gas_price(&self, base_fee: U256) -> U256 {
+        match self.max_fee_per_gas {
+            Some(max_fee) => {
+                max_fee.min(base_fee + self.max_priority_fee_per_gas.unwrap_or_default())
+            }
+            None => self.gas_price.unwrap_or(base_fee),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct BlockRecord {
+    pub number: u64,
+    pub hash: H256,
+    pub timestamp: u64,
+    pub gas_used: U256,
+    pub transactions: Vec<H256>,
+}
+
+impl BlockRecord {
+    fn new(number: u64, timestamp: u64) -> Self {
+        let mut preimage = number.to_be_bytes().to_vec();
+        preimage.extend(timestamp.to_be_bytes());
+        Self {
+            number,
+            hash: H256::from(keccak256(preimage)),
+            timestamp,
+            gas_used: U256::zero(),
+            transactions: Vec::new(),
+        }
+    }
+}
+
+/// Chain state, every transaction is mined immediately in a block of its own
+#[derive(Debug)]
+pub(crate) struct Chain {
+    db: CacheDB<EmptyDB>,
+    blocks: Vec<BlockRecord>,
+    transactions: HashMap<H256, (Transaction, TransactionReceipt)>,
+    logs: Vec<Log>,
+}
+
+impl Chain {
+    pub fn new(accounts: &[Address], genesis_timestamp: u64) -> Self {
+        let mut db = CacheDB::new(EmptyDB::default());
+        for account in accounts {
+            db.insert_account_info(
+                evm_address(*account),
+                AccountInfo {
+                    balance: EvmU256::from(DEV_BALANCE),
+                    ..Default::default()
+                },
+            );
+        }
+        Self {
+            db,
+            blocks: vec![BlockRecord::new(0, genesis_timestamp)],
+            transactions: HashMap::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn latest(&self) -> &BlockRecord {
+        self.blocks.last().expect("genesis block")
+    }
+
+    pub fn block_number(&self, number: BlockNumber) -> u64 {
+        match number {
+            BlockNumber::Earliest => 0,
+            BlockNumber::Number(number) => number.as_u64(),
+            _ => self.latest().number,
+        }
+    }
+
+    /// state is only kept for the latest block
+    pub fn ensure_latest(&self, number: Option<BlockNumber>) -> Result<(), DevChainError> {
+        match number {
+            Some(number) if self.block_number(number) != self.latest().number => Err(
+                DevChainError::invalid_params("historical state is not available"),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn block(&self, number: BlockNumber) -> Option<&BlockRecord> {
+        self.blocks
+            .get(usize::try_from(self.block_number(number)).ok()?)
+    }
+
+    pub fn block_by_hash(&self, hash: H256) -> Option<&BlockRecord> {
+        self.blocks.iter().find(|block| block.hash == hash)
+    }
+
+    pub fn header(&self, record: &BlockRecord) -> Block<H256> {
+        let parent_hash = match record.number {
+            0 => H256::zero(),
+            number => self.blocks[number as usize - 1].hash,
+        };
+        Block {
+            hash: Some(record.hash),
+            parent_hash,
+            number: Some(record.number.into()),
+            timestamp: record.timestamp.into(),
+            gas_limit: GAS_LIMIT.into(),
+            gas_used: record.gas_used,
+            base_fee_per_gas: Some(BASE_FEE.into()),
+            transactions: record.transactions.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn balance(&mut self, address: Address) -> U256 {
+        self.account(address)
+            .map(|info| U256(info.balance.into_limbs()))
+            .unwrap_or_default()
+    }
+
+    pub fn nonce(&mut self, address: Address) -> u64 {
+        self.account(address)
+            .map(|info| info.nonce)
+            .unwrap_or_default()
+    }
+
+    pub fn code(&mut self, address: Address) -> Bytes {
+        self.account(address)
+            .and_then(|info| info.code)
+            .map(|code| code.original_bytes().to_vec().into())
+            .unwrap_or_default()
+    }
+
+    fn account(&mut self, address: Address) -> Option<AccountInfo> {
+        self.db
+            .load_account(evm_address(address))
+            .ok()
+            .map(|account| account.info.clone())
+    }
+
+    pub fn transaction(&self, hash: H256) -> Option<&Transaction> {
+        self.transactions
+            .get(&hash)
+            .map(|(transaction, _)| transaction)
+    }
+
+    pub fn receipt(&self, hash: H256) -> Option<&TransactionReceipt> {
+        self.transactions.get(&hash).map(|(_, receipt)| receipt)
+    }
+
+    /// execute `request` against the latest state without committing it
+    pub fn call(&mut self, request: &CallRequest) -> Result<ExecutionResult, DevChainError> {
+        let latest = self.latest();
+        let block = block_env(latest.number, latest.timestamp, U256::zero());
+        let request = CallRequest {
+            gas_price: Some(U256::zero()),
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            nonce: None,
+            ..request.clone()
+        };
+        self.transact(&request, block, false)
+    }
+
+    /// execute `request` in a new block, returning the transaction hash
+    ///
+    /// Transactions that fail validation, such as for a wrong nonce, are
+    /// rejected without mining a block.  Reverted transactions are mined with a
+    /// failed status like on any other chain.
+    pub fn send(
+        &mut self,
+        request: CallRequest,
+        hash: Option<H256>,
+        signature: Option<Signature>,
+        transaction_type: u64,
+    ) -> Result<H256, DevChainError> {
+        let from = request.from.unwrap_or_default();
+        let nonce = request.nonce.unwrap_or_else(|| self.nonce(from).into());
+        // unpriced transactions pay the base fee
+        let gas_price = match request.max_fee_per_gas {
+            Some(_) => None,
+            None => Some(request.gas_price.unwrap_or(BASE_FEE.into())),
+        };
+        let request = CallRequest {
+            nonce: Some(nonce),
+            gas_price,
+            ..request
+        };
+        let hash = hash.unwrap_or_else(|| {
+            let mut preimage = [0u8; 52];
+            preimage[..20].copy_from_slice(from.as_bytes());
+            nonce.to_big_endian(&mut preimage[20..]);
+            H256::from(keccak256(preimage))
+        });
+        let mut block = BlockRecord::new(
+            self.latest().number + 1,
+            self.latest().timestamp + BLOCK_TIME,
+        );
+        let env = block_env(block.number, block.timestamp, BASE_FEE.into());
+        let result = self.transact(&request, env, true)?;
+
+        let (status, gas_used, evm_logs, contract_address) = match result {
+            ExecutionResult::Success {
+                gas_used,
+                logs,
+                output,
+                ..
+            } => {
+                let created = match output {
+                    Output::Create(_, address) => address.map(eth_address),
+                    Output::Call(_) => None,
+                };
+                (1, gas_used, logs, created)
+            }
+            ExecutionResult::Revert { gas_used, .. } | ExecutionResult::Halt { gas_used, .. } => {
+                (0, gas_used, Vec::new(), None)
+            }
+        };
+        let logs = evm_logs
+            .into_iter()
+            .enumerate()
+            .map(|(index, log)| Log {
+                address: eth_address(log.address),
+                topics: log
+                    .data
+                    .topics()
+                    .iter()
+                    .map(|topic| H256::from(topic.0))
+                    .collect(),
+                data: log.data.data.to_vec().into(),
+                block_hash: Some(block.hash),
+                block_number: Some(block.number.into()),
+                transaction_hash: Some(hash),
+                transaction_index: Some(U64::zero()),
+                log_index: Some(index.into()),
+                transaction_log_index: Some(index.into()),
+                removed: Some(false),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let effective_gas_price = request.effective_gas_price(BASE_FEE.into());
+        let receipt = TransactionReceipt {
+            transaction_hash: hash,
+            transaction_index: U64::zero(),
+            block_hash: Some(block.hash),
+            block_number: Some(block.number.into()),
+            from,
+            to: request.to,
+            cumulative_gas_used: gas_used.into(),
+            gas_used: Some(gas_used.into()),
+            contract_address,
+            logs: logs.clone(),
+            status: Some(status.into()),
+            transaction_type: Some(transaction_type.into()),
+            effective_gas_price: Some(effective_gas_price),
+            ..Default::default()
+        };
+        let transaction = Transaction {
+            hash,
+            nonce,
+            block_hash: Some(block.hash),
+            block_number: Some(block.number.into()),
+            transaction_index: Some(U64::zero()),
+            from,
+            to: request.to,
+            value: request.value.unwrap_or_default(),
+            gas_price: Some(effective_gas_price),
+            gas: request.gas.unwrap_or(GAS_LIMIT.into()),
+            input: request.data.clone().unwrap_or_default(),
+            v: signature
+                .map(|signature| signature.v)
+                .unwrap_or_default()
+                .into(),
+            r: signature.map(|signature| signature.r).unwrap_or_default(),
+            s: signature.map(|signature| signature.s).unwrap_or_default(),
+            transaction_type: Some(transaction_type.into()),
+            max_fee_per_gas: request.max_fee_per_gas,
+            max_priority_fee_per_gas: request.max_priority_fee_per_gas,
+            chain_id: Some(DEV_CHAIN_ID.into()),
+            ..Default::default()
+        };
+
+        block.gas_used = gas_used.into();
+        block.transactions.push(hash);
+        self.blocks.push(block);
+        self.logs.extend(logs);
+        self.transactions.insert(hash, (transaction, receipt));
+        Ok(hash)
+    }
+
+    /// mine an empty block `seconds` after the latest one
+    pub fn mine(&mut self, seconds: u64) {
+        let latest = self.latest();
+        let block = BlockRecord::new(latest.number + 1, latest.timestamp + seconds);
+        self.blocks.push(block);
+    }
+
+    /// the first and last block `filter` covers, `None` for an unknown block hash
+    pub fn block_range(&self, filter: &Filter) -> Option<(u64, u64)> {
+        match filter.block_option {
+            FilterBlockOption::Range {
+                from_block,
+                to_block,
+            } => Some((
+                self.block_number(from_block.unwrap_or_default()),
+                self.block_number(to_block.unwrap_or_default()),
+            )),
+            FilterBlockOption::AtBlockHash(hash) => self
+                .block_by_hash(hash)
+                .map(|block| (block.number, block.number)),
+        }
+    }
+
+    pub fn logs(&self, filter: &Filter) -> Vec<Log> {
+        let Some((from, to)) = self.block_range(filter) else {
+            return Vec::new();
+        };
+        self.logs
+            .iter()
+            .filter(|log| {
+                let number = log.block_number.unwrap_or_default().as_u64();
+                from <= number && number <= to
+            })
+            .filter(|log| match &filter.address {
+                Some(ValueOrArray::Value(address)) => log.address == *address,
+                Some(ValueOrArray::Array(addresses)) => addresses.contains(&log.address),
+                None => true,
+            })
+            .filter(|log| {
+                filter.topics.iter().enumerate().all(|(index, topic)| {
+                    let actual = log.topics.get(index);
+                    match topic {
+                        None | Some(ValueOrArray::Value(None)) => true,
+                        Some(ValueOrArray::Value(Some(expected))) => actual == Some(expected),
+                        Some(ValueOrArray::Array(expected)) => expected
+                            .iter()
+                            .any(|expected| expected.is_none() || actual == expected.as_ref()),
+                    }
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn transact(
+        &mut self,
+        request: &CallRequest,
+        block: BlockEnv,
+        commit: bool,
+    ) -> Result<ExecutionResult, DevChainError> {
+        let mut evm = Evm::builder()
+            .with_db(&mut self.db)
+            .with_spec_id(SpecId::SHANGHAI)
+            .modify_cfg_env(|cfg| cfg.chain_id = DEV_CHAIN_ID)
+            .with_block_env(block)
+            .modify_tx_env(|tx| {
+                tx.caller = evm_address(request.from.unwrap_or_default());
+                tx.transact_to = match request.to {
+                    Some(to) => TransactTo::call(evm_address(to)),
+                    None => TransactTo::create(),
+                };
+                tx.value = evm_u256(request.value.unwrap_or_default());
+                tx.data = request.data.clone().unwrap_or_default().to_vec().into();
+                tx.gas_limit = request
+                    .gas
+                    .map(|gas| gas.min(GAS_LIMIT.into()).as_u64())
+                    .unwrap_or(GAS_LIMIT);
+                match request.max_fee_per_gas {
+                    Some(max_fee) => {
+                        tx.gas_price = evm_u256(max_fee);
+                        tx.gas_priority_fee = Some(evm_u256(
+                            request.max_priority_fee_per_gas.unwrap_or_default(),
+                        ));
+                    }
+                    None => tx.gas_price = evm_u256(request.gas_price.unwrap_or_default()),
+                }
+                tx.nonce = request.nonce.map(|nonce| nonce.as_u64());
+            })
+            .build();
+        let result = if commit {
+            evm.transact_commit()
+        } else {
+            evm.transact().map(|result| result.result)
+        };
+        result.map_err(|err| DevChainError::rpc(-32000, err.to_string()))
+    }
+}
+
+fn block_env(number: u64, timestamp: u64, base_fee: U256) -> BlockEnv {
+    BlockEnv {
+        number: EvmU256::from(number),
+        timestamp: EvmU256::from(timestamp),
+        gas_limit: EvmU256::from(GAS_LIMIT),
+        basefee: evm_u256(base_fee),
+        prevrandao: Some(B256::ZERO),
+        ..Default::default()
+    }
+}
+
+fn evm_address(address: Address) -> revm::primitives::Address {
+    revm::primitives::Address::from(address.0)
+}
+
+fn eth_address(address: revm::primitives::Address) -> Address {
+    Address::from(address.0 .0)
+}
+
+fn evm_u256(value: U256) -> EvmU256 {
+    EvmU256::from_limbs(value.0)
+}
@@ -0,0 +1,58 @@
+use ethers::{
+    providers::{JsonRpcError, ProviderError, RpcError},
+    types::Bytes,
+};
+
+/// Errors reported by the in-process chain, shaped like node responses
+#[derive(Debug, thiserror::Error)]
+pub enum DevChainError {
+    #[error(transparent)]
+    JsonRpc(#[from] JsonRpcError),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+}
+
+impl DevChainError {
+    pub(crate) fn rpc(code: i64, message: impl Into<String>) -> Self {
+        DevChainError::JsonRpc(JsonRpcError {
+            code,
+            message: message.into(),
+            data: None,
+        })
+    }
+
+    pub(crate) fn invalid_params(message: impl Into<String>) -> Self {
+        Self::rpc(-32602, message)
+    }
+
+    /// a revert with its return data, which contract bindings decode
+    pub(crate) fn revert(output: Bytes) -> Self {
+        DevChainError::JsonRpc(JsonRpcError {
+            code: 3,
+            message: "execution reverted".to_string(),
+            data: Some(serde_json::Value::String(output.to_string())),
+        })
+    }
+}
+
+impl RpcError for DevChainError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            DevChainError::JsonRpc(err) => Some(err),
+            DevChainError::Serde(_) => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            DevChainError::Serde(err) => Some(err),
+            DevChainError::JsonRpc(_) => None,
+        }
+    }
+}
+
+impl From<DevChainError> for ProviderError {
+    fn from(err: DevChainError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
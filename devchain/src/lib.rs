@@ -0,0 +1,460 @@
+//! An in-process EVM chain for tests
+//!
+//! `DevChain` answers JSON-RPC requests from a `revm` instance, so it can back
+//! an ethers `Provider` and everything built on one without a node or network.
+//! Transactions are mined immediately, one per block, and the well known
+//! development accounts are funded at genesis.
+
+use async_trait::async_trait;
+use ethers::{
+    prelude::{LocalWallet, Provider, SignerMiddleware},
+    providers::JsonRpcClient,
+    signers::Signer,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, Bytes, FeeHistory, Filter,
+        Transaction, H256, U256, U64,
+    },
+    utils::{keccak256, rlp::Rlp},
+};
+use revm::primitives::{ExecutionResult, Output};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+mod chain;
+mod error;
+
+use chain::{CallRequest, Chain, BASE_FEE};
+
+pub use error::DevChainError;
+
+pub const DEV_CHAIN_ID: u64 = 31337;
+pub const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+/// seconds between mined blocks
+pub const BLOCK_TIME: u64 = 12;
+
+/// Private keys of the funded accounts, the anvil and hardhat development keys
+pub const DEV_KEYS: [&str; 4] = [
+    "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d",
+    "5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a",
+    "7c852118294e51e653712a81e05800f419141751be58f605c371e15141b007a6",
+];
+
+const REGISTRY_ARTIFACT: &str = include_str!("../../abi/DIDRegistry.json");
+
+/// A local chain, clones share the same state
+#[derive(Clone)]
+pub struct DevChain {
+    chain: Arc<Mutex<Chain>>,
+    registry: Option<Address>,
+    max_log_range: Option<u64>,
+}
+
+impl fmt::Debug for DevChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevChain")
+            .field("block_number", &self.block_number())
+            .field("registry", &self.registry)
+            .field("max_log_range", &self.max_log_range)
+            .finish()
+    }
+}
+
+impl Default for DevChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DevChain {
+    pub fn new() -> Self {
+        let accounts = (0..DEV_KEYS.len())
+            .map(|index| Self::wallet(index).address())
+            .collect::<Vec<_>>();
+        Self {
+            chain: Arc::new(Mutex::new(Chain::new(&accounts, GENESIS_TIMESTAMP))),
+            registry: None,
+            max_log_range: None,
+        }
+    }
+
+    /// a chain with the bundled `DIDRegistry` deployed by the first dev account,
+    /// at the address of the well known dev network deployment
+    pub fn with_registry() -> Self {
+        let mut dev_chain = Self::new();
+        let artifact: Value = serde_json::from_str(REGISTRY_ARTIFACT).expect("registry artifact");
+        let bytecode = artifact["bytecode"]
+            .as_str()
+            .and_then(|bytecode| bytecode.parse::<Bytes>().ok())
+            .expect("registry bytecode");
+        let request = CallRequest {
+            from: Some(Self::wallet(0).address()),
+            data: Some(bytecode),
+            ..Default::default()
+        };
+        let mut chain = dev_chain.lock();
+        let hash = chain
+            .send(request, None, None, 0)
+            .expect("registry deployment");
+        let registry = chain
+            .receipt(hash)
+            .and_then(|receipt| receipt.contract_address);
+        drop(chain);
+        dev_chain.registry = registry;
+        dev_chain
+    }
+
+    /// reject `eth_getLogs` spanning more than `blocks` blocks with a limit exceeded
+    /// error, like a hosted provider, the chain state is still shared with other clones
+    pub fn with_max_log_range(mut self, blocks: u64) -> Self {
+        self.max_log_range = Some(blocks);
+        self
+    }
+
+    /// the address of the registry deployed by `with_registry`
+    pub fn registry(&self) -> Option<Address> {
+        self.registry
+    }
+
+    /// the wallet of dev account `index`
+    pub fn wallet(index: usize) -> LocalWallet {
+        DEV_KEYS[index]
+            .parse::<LocalWallet>()
+            .expect("dev key")
+            .with_chain_id(DEV_CHAIN_ID)
+    }
+
+    pub fn provider(&self) -> Provider<DevChain> {
+        // transactions are mined immediately, there is nothing to wait for
+        Provider::new(self.clone()).interval(Duration::from_millis(1))
+    }
+
+    /// a provider signing with dev account `index`
+    pub fn signer(&self, index: usize) -> SignerMiddleware<Provider<DevChain>, LocalWallet> {
+        SignerMiddleware::new(self.provider(), Self::wallet(index))
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.lock().latest().number
+    }
+
+    /// the timestamp of the latest block
+    pub fn timestamp(&self) -> u64 {
+        self.lock().latest().timestamp
+    }
+
+    /// mine an empty block
+    pub fn mine(&self) {
+        self.lock().mine(BLOCK_TIME);
+    }
+
+    /// mine an empty block `seconds` after the latest one
+    pub fn advance_time(&self, seconds: u64) {
+        self.lock().mine(seconds);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Chain> {
+        self.chain
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, DevChainError> {
+        let mut chain = self.lock();
+        let result = match method {
+            "eth_chainId" => to_value(U64::from(DEV_CHAIN_ID))?,
+            "net_version" => to_value(DEV_CHAIN_ID.to_string())?,
+            "eth_accounts" => to_value(
+                (0..DEV_KEYS.len())
+                    .map(|index| Self::wallet(index).address())
+                    .collect::<Vec<_>>(),
+            )?,
+            "eth_blockNumber" => to_value(U64::from(chain.latest().number))?,
+            "eth_gasPrice" => to_value(U256::from(BASE_FEE * 2))?,
+            "eth_maxPriorityFeePerGas" => to_value(U256::from(BASE_FEE))?,
+            "eth_feeHistory" => {
+                let count = param::<U256>(params, 0)?.as_u64().max(1) as usize;
+                let latest = chain.latest().number;
+                to_value(FeeHistory {
+                    base_fee_per_gas: vec![BASE_FEE.into(); count + 1],
+                    gas_used_ratio: vec![0.0; count],
+                    oldest_block: latest.saturating_sub(count as u64 - 1).into(),
+                    reward: vec![vec![BASE_FEE.into()]; count],
+                })?
+            }
+            "eth_getBalance" => {
+                chain.ensure_latest(param(params, 1)?)?;
+                to_value(chain.balance(param(params, 0)?))?
+            }
+            "eth_getTransactionCount" => {
+                chain.ensure_latest(param(params, 1)?)?;
+                to_value(U256::from(chain.nonce(param(params, 0)?)))?
+            }
+            "eth_getCode" => {
+                chain.ensure_latest(param(params, 1)?)?;
+                to_value(chain.code(param(params, 0)?))?
+            }
+            "eth_getBlockByNumber" => {
+                let block = chain.block(param(params, 0)?).cloned();
+                match block {
+                    Some(block) => block_value(&chain, &chain.header(&block), param(params, 1)?)?,
+                    None => Value::Null,
+                }
+            }
+            "eth_getBlockByHash" => {
+                let block = chain.block_by_hash(param(params, 0)?).cloned();
+                match block {
+                    Some(block) => block_value(&chain, &chain.header(&block), param(params, 1)?)?,
+                    None => Value::Null,
+                }
+            }
+            "eth_call" => {
+                chain.ensure_latest(param(params, 1)?)?;
+                match chain.call(&param(params, 0)?)? {
+                    ExecutionResult::Success {
+                        output: Output::Call(output),
+                        ..
+                    } => to_value(Bytes::from(output.to_vec()))?,
+                    result => return Err(failure(result)),
+                }
+            }
+            "eth_estimateGas" => match chain.call(&param(params, 0)?)? {
+                // leave room for refunds and the 63/64 rule of nested calls
+                ExecutionResult::Success { gas_used, .. } => {
+                    to_value(U256::from(gas_used + gas_used / 2))?
+                }
+                result => return Err(failure(result)),
+            },
+            "eth_sendTransaction" => {
+                let request: CallRequest = param(params, 0)?;
+                let transaction_type = match request.max_fee_per_gas {
+                    Some(_) => 2,
+                    None => 0,
+                };
+                to_value(chain.send(request, None, None, transaction_type)?)?
+            }
+            "eth_sendRawTransaction" => {
+                let raw: Bytes = param(params, 0)?;
+                let (transaction, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw))
+                    .map_err(|err| DevChainError::invalid_params(err.to_string()))?;
+                let from = signature
+                    .recover(transaction.sighash())
+                    .map_err(|err| DevChainError::invalid_params(err.to_string()))?;
+                let request = call_request(&transaction, from);
+                let transaction_type = match transaction {
+                    TypedTransaction::Legacy(_) => 0,
+                    TypedTransaction::Eip2930(_) => 1,
+                    TypedTransaction::Eip1559(_) => 2,
+                };
+                let hash = H256::from(keccak256(&raw));
+                to_value(chain.send(request, Some(hash), Some(signature), transaction_type)?)?
+            }
+            "eth_getTransactionByHash" => to_value(chain.transaction(param(params, 0)?))?,
+            "eth_getTransactionReceipt" => to_value(chain.receipt(param(params, 0)?))?,
+            "eth_getLogs" => {
+                let filter = param::<Filter>(params, 0)?;
+                if let (Some(max_log_range), Some((from, to))) =
+                    (self.max_log_range, chain.block_range(&filter))
+                {
+                    if to.saturating_sub(from) >= max_log_range {
+                        return Err(DevChainError::rpc(
+                            -32005,
+                            format!("block range is limited to {max_log_range} blocks"),
+                        ));
+                    }
+                }
+                to_value(chain.logs(&filter))?
+            }
+            _ => {
+                return Err(DevChainError::rpc(
+                    -32601,
+                    format!("method {method} is not supported"),
+                ))
+            }
+        };
+        tracing::debug!("{method}: {result}");
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for DevChain {
+    type Error = DevChainError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+        let result = self.dispatch(method, &params)?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+fn param<T: DeserializeOwned>(params: &Value, index: usize) -> Result<T, DevChainError> {
+    let value = params.get(index).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|err| DevChainError::invalid_params(err.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, DevChainError> {
+    Ok(serde_json::to_value(value)?)
+}
+
+/// a block with transaction hashes, or with full transactions when `full` is set
+fn block_value(chain: &Chain, header: &Block<H256>, full: bool) -> Result<Value, DevChainError> {
+    let mut block = to_value(header)?;
+    if full {
+        let transactions = header
+            .transactions
+            .iter()
+            .filter_map(|hash| chain.transaction(*hash).cloned())
+            .collect::<Vec<Transaction>>();
+        block["transactions"] = to_value(transactions)?;
+    }
+    Ok(block)
+}
+
+fn call_request(transaction: &TypedTransaction, from: Address) -> CallRequest {
+    let (max_fee_per_gas, max_priority_fee_per_gas) = match transaction {
+        TypedTransaction::Eip1559(transaction) => (
+            transaction.max_fee_per_gas,
+            transaction.max_priority_fee_per_gas,
+        ),
+        _ => (None, None),
+    };
+    CallRequest {
+        from: Some(from),
+        to: transaction.to_addr().copied(),
+        gas: transaction.gas().copied(),
+        gas_price: max_fee_per_gas
+            .is_none()
+            .then(|| transaction.gas_price())
+            .flatten(),
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+        value: transaction.value().copied(),
+        data: transaction.data().cloned(),
+        nonce: transaction.nonce().copied(),
+    }
+}
+
+fn failure(result: ExecutionResult) -> DevChainError {
+    match result {
+        ExecutionResult::Revert { output, .. } => DevChainError::revert(output.to_vec().into()),
+        ExecutionResult::Halt { reason, .. } => DevChainError::rpc(-32000, format!("{reason:?}")),
+        ExecutionResult::Success { .. } => DevChainError::rpc(-32000, "unexpected output"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{Middleware, RpcError},
+        types::BlockNumber,
+    };
+
+    #[test]
+    fn test_dev_accounts() {
+        let expected = [
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC",
+            "0x90F79bf6EB2c4f870365E785982E1f101E93b906",
+        ];
+        for (index, address) in expected.into_iter().enumerate() {
+            assert_eq!(DevChain::wallet(index).address(), address.parse().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_deployment() {
+        let dev_chain = DevChain::with_registry();
+        let registry = dev_chain.registry().unwrap();
+        assert_eq!(
+            registry,
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+                .parse()
+                .unwrap()
+        );
+        let provider = dev_chain.provider();
+        let artifact: Value = serde_json::from_str(REGISTRY_ARTIFACT).unwrap();
+        let code = provider.get_code(registry, None).await.unwrap();
+        assert_eq!(
+            code,
+            artifact["deployedBytecode"]
+                .as_str()
+                .unwrap()
+                .parse::<Bytes>()
+                .unwrap()
+        );
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::one());
+    }
+
+    #[tokio::test]
+    async fn test_transfer() {
+        let dev_chain = DevChain::new();
+        let signer = dev_chain.signer(0);
+        let to = DevChain::wallet(1).address();
+        let before = signer.get_balance(to, None).await.unwrap();
+        let tx = ethers::types::TransactionRequest::new().to(to).value(1_000);
+        let receipt = signer
+            .send_transaction(tx, None)
+            .await
+            .unwrap()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(receipt.status, Some(U64::one()));
+        assert_eq!(receipt.block_number, Some(U64::one()));
+        assert_eq!(signer.get_balance(to, None).await.unwrap(), before + 1_000);
+        assert_eq!(
+            signer
+                .get_transaction_count(DevChain::wallet(0).address(), None)
+                .await
+                .unwrap(),
+            U256::one()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_advance_time() {
+        let dev_chain = DevChain::new();
+        dev_chain.advance_time(3600);
+        let block = dev_chain
+            .provider()
+            .get_block(BlockNumber::Latest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.timestamp, U256::from(GENESIS_TIMESTAMP + 3600));
+        assert_eq!(dev_chain.block_number(), 1);
+    }
+    #[tokio::test]
+    async fn test_max_log_range() {
+        let dev_chain = DevChain::new();
+        for _ in 0..10 {
+            dev_chain.mine();
+        }
+        let limited = Provider::new(dev_chain.clone().with_max_log_range(5));
+        let filter = Filter::new().from_block(0).to_block(4);
+        assert!(limited.get_logs(&filter).await.unwrap().is_empty());
+        let err = limited
+            .get_logs(&Filter::new().from_block(0).to_block(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.as_error_response().map(|err| err.code), Some(-32005));
+        // other clones of the chain are not limited
+        assert!(dev_chain
+            .provider()
+            .get_logs(&Filter::new().from_block(0).to_block(10))
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,59 @@
+//! Reading an identity's history with different `eth_getLogs` strategies
+//!
+//! Each strategy replays a cassette recorded by `examples/record_history.rs`:
+//! 100 changes by the first dev account to the bundled DIDRegistry, spread over
+//! 90,000 blocks of a `DevChain`.  The split strategy was recorded against a
+//! chain rejecting log queries over 2,000 blocks.  Responses are served from
+//! the cassettes, so the benchmark measures request count and decoding rather
+//! than network latency.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use devchain::DevChain;
+use dideth::{
+    Cassette, Did, Network, RegistryConfig, ReplayTransport, Resolver, DEV_CHAIN_ID, LOG_RANGE,
+};
+use ethers::{prelude::Provider, signers::Signer};
+use std::{path::Path, sync::Arc};
+
+fn cassette(name: &str) -> Cassette {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("benches/fixtures")
+        .join(format!("{name}.json"));
+    Cassette::load(path).expect("history cassette")
+}
+
+fn bench_history(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let did = Did::new(DEV_CHAIN_ID, DevChain::wallet(0).address());
+    let strategies = [
+        ("per_block", 1),
+        ("batched", LOG_RANGE),
+        ("split", LOG_RANGE),
+    ];
+    let mut group = c.benchmark_group("history");
+    for (name, log_range) in strategies {
+        let cassette = cassette(name);
+        let requests = cassette
+            .interactions
+            .iter()
+            .filter(|interaction| interaction.method == "eth_getLogs")
+            .count();
+        println!("history/{name}: {requests} eth_getLogs requests");
+        let config = RegistryConfig::for_network(Network::Dev).with_log_range(log_range);
+        let provider = Provider::new(ReplayTransport::new(cassette));
+        let resolver = runtime
+            .block_on(Resolver::from_client(Arc::new(provider), config))
+            .unwrap();
+        assert_eq!(runtime.block_on(resolver.history(&did)).unwrap().len(), 100);
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.to_async(&runtime).iter(|| resolver.history(&did))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_history);
+criterion_main!(benches);
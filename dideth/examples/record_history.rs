@@ -0,0 +1,91 @@
+//! Record the cassettes the history benchmark replays
+//!
+//! Makes 100 registry changes for the first dev account on the bundled
+//! DIDRegistry, spread over 90,000 blocks of a `DevChain`, then records the
+//! requests of `Resolver::history` for each log strategy.  The split strategy
+//! runs against a chain rejecting log queries over 2,000 blocks.
+//!
+//! ```
+//! $ (cd dideth && cargo run --example record_history)
+//! ```
+
+use devchain::DevChain;
+use dideth::{
+    DelegateType, Did, DidEthRegistry, Network, RecordingTransport, RegistryConfig, Resolver,
+    DEV_CHAIN_ID, LOG_RANGE,
+};
+use ethers::{prelude::Provider, signers::Signer};
+use std::{error::Error, fs, path::Path, sync::Arc};
+
+const CHANGES: usize = 100;
+const BLOCKS_BETWEEN_CHANGES: usize = 899;
+const MAX_LOG_RANGE: u64 = 2_000;
+
+fn config() -> RegistryConfig {
+    RegistryConfig::for_network(Network::Dev).with_confirmations(1)
+}
+
+async fn make_changes(dev_chain: &DevChain) -> Result<(), Box<dyn Error>> {
+    let registry = DidEthRegistry::from_client(Arc::new(dev_chain.signer(0)), config()).await?;
+    for change in 0..CHANGES {
+        for _ in 0..BLOCKS_BETWEEN_CHANGES {
+            dev_chain.mine();
+        }
+        let delegate = format!("{:#x}", DevChain::wallet(1 + change % 3).address());
+        let endpoint = format!("https://example.com/{change}");
+        match change % 4 {
+            0 | 1 => {
+                registry
+                    .set_attribute("did/svc/LinkedDomains".to_string(), endpoint)
+                    .await?
+            }
+            2 => {
+                registry
+                    .add_delegate(DelegateType::SigAuth, delegate, 86_400)
+                    .await?
+            }
+            _ => {
+                registry
+                    .revoke_attribute(
+                        "did/svc/LinkedDomains".to_string(),
+                        format!("https://example.com/{}", change - 3),
+                    )
+                    .await?
+            }
+        };
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let dev_chain = DevChain::with_registry();
+    make_changes(&dev_chain).await?;
+    let did = Did::new(DEV_CHAIN_ID, DevChain::wallet(0).address());
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/fixtures");
+    fs::create_dir_all(&fixtures)?;
+    let strategies = [
+        ("per_block", 1, dev_chain.clone()),
+        ("batched", LOG_RANGE, dev_chain.clone()),
+        (
+            "split",
+            LOG_RANGE,
+            dev_chain.clone().with_max_log_range(MAX_LOG_RANGE),
+        ),
+    ];
+    for (name, log_range, chain) in strategies {
+        let recorder = RecordingTransport::new(chain);
+        let provider = Arc::new(Provider::new(recorder.clone()));
+        let resolver = Resolver::from_client(provider, config().with_log_range(log_range)).await?;
+        let history = resolver.history(&did).await?;
+        assert_eq!(history.len(), CHANGES);
+        let path = fixtures.join(format!("{name}.json"));
+        recorder.cassette().save(&path)?;
+        println!(
+            "{name}: {} requests to {}",
+            recorder.cassette().interactions.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
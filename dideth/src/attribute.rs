@@ -0,0 +1,292 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{fmt, str::FromStr};
+
+use crate::{DidEthError, VerificationMethodType};
+
+/// Key algorithms of `did/pub` attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAlgorithm {
+    Secp256k1,
+    Ed25519,
+    X25519,
+    Rsa,
+}
+
+impl KeyAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyAlgorithm::Secp256k1 => "Secp256k1",
+            KeyAlgorithm::Ed25519 => "Ed25519",
+            KeyAlgorithm::X25519 => "X25519",
+            KeyAlgorithm::Rsa => "RSA",
+        }
+    }
+
+    pub fn method_type(&self) -> VerificationMethodType {
+        match self {
+            KeyAlgorithm::Secp256k1 => VerificationMethodType::EcdsaSecp256k1VerificationKey2019,
+            KeyAlgorithm::Ed25519 => VerificationMethodType::Ed25519VerificationKey2018,
+            KeyAlgorithm::X25519 => VerificationMethodType::X25519KeyAgreementKey2019,
+            KeyAlgorithm::Rsa => VerificationMethodType::RsaVerificationKey2018,
+        }
+    }
+}
+
+impl FromStr for KeyAlgorithm {
+    type Err = DidEthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Secp256k1" => Ok(KeyAlgorithm::Secp256k1),
+            "Ed25519" => Ok(KeyAlgorithm::Ed25519),
+            "X25519" => Ok(KeyAlgorithm::X25519),
+            "RSA" => Ok(KeyAlgorithm::Rsa),
+            _ => Err(DidEthError::InvalidAttributeName(s.to_string())),
+        }
+    }
+}
+
+/// The verification relationship a `did/pub` key is published for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyPurpose {
+    /// assertionMethod
+    VeriKey,
+    /// authentication and assertionMethod
+    SigAuth,
+    /// keyAgreement
+    Enc,
+}
+
+impl KeyPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyPurpose::VeriKey => "veriKey",
+            KeyPurpose::SigAuth => "sigAuth",
+            KeyPurpose::Enc => "enc",
+        }
+    }
+}
+
+impl FromStr for KeyPurpose {
+    type Err = DidEthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "veriKey" => Ok(KeyPurpose::VeriKey),
+            "sigAuth" => Ok(KeyPurpose::SigAuth),
+            "enc" => Ok(KeyPurpose::Enc),
+            _ => Err(DidEthError::InvalidAttributeName(s.to_string())),
+        }
+    }
+}
+
+/// How a `did/pub` key is represented in the document, the on-chain value is always the raw key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyEncoding {
+    Hex,
+    Base58,
+    Base64,
+    Multibase,
+}
+
+impl KeyEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyEncoding::Hex => "hex",
+            KeyEncoding::Base58 => "base58",
+            KeyEncoding::Base64 => "base64",
+            KeyEncoding::Multibase => "multibase",
+        }
+    }
+}
+
+impl FromStr for KeyEncoding {
+    type Err = DidEthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(KeyEncoding::Hex),
+            "base58" => Ok(KeyEncoding::Base58),
+            "base64" => Ok(KeyEncoding::Base64),
+            "multibase" => Ok(KeyEncoding::Multibase),
+            _ => Err(DidEthError::InvalidAttributeName(s.to_string())),
+        }
+    }
+}
+
+/// Public key material in one of the document encodings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    Hex(String),
+    Base58(String),
+    Base64(String),
+    /// base58btc multibase, prefixed with `z`
+    Multibase(String),
+}
+
+impl PublicKey {
+    pub fn encode(encoding: KeyEncoding, value: &[u8]) -> Self {
+        match encoding {
+            KeyEncoding::Hex => PublicKey::Hex(hex::encode(value)),
+            KeyEncoding::Base58 => PublicKey::Base58(bs58::encode(value).into_string()),
+            KeyEncoding::Base64 => PublicKey::Base64(STANDARD.encode(value)),
+            KeyEncoding::Multibase => {
+                PublicKey::Multibase(format!("z{}", bs58::encode(value).into_string()))
+            }
+        }
+    }
+}
+
+/// A parsed `did/pub/<algorithm>/<purpose>/<encoding>` attribute name
+///
+/// The purpose defaults to `veriKey` and the encoding to `hex`, as in the
+/// did:ethr specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKeyAttribute {
+    pub algorithm: KeyAlgorithm,
+    pub purpose: KeyPurpose,
+    pub encoding: KeyEncoding,
+}
+
+impl PublicKeyAttribute {
+    pub fn public_key(&self, value: &[u8]) -> PublicKey {
+        PublicKey::encode(self.encoding, value)
+    }
+}
+
+impl fmt::Display for PublicKeyAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "did/pub/{}/{}/{}",
+            self.algorithm.as_str(),
+            self.purpose.as_str(),
+            self.encoding.as_str()
+        )
+    }
+}
+
+impl FromStr for PublicKeyAttribute {
+    type Err = DidEthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DidEthError::InvalidAttributeName(s.to_string());
+        let mut parts = s.strip_prefix("did/pub/").ok_or_else(invalid)?.split('/');
+        let algorithm = parts
+            .next()
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        let purpose = match parts.next() {
+            Some(purpose) => purpose.parse().map_err(|_| invalid())?,
+            None => KeyPurpose::VeriKey,
+        };
+        let encoding = match parts.next() {
+            Some(encoding) => encoding.parse().map_err(|_| invalid())?,
+            None => KeyEncoding::Hex,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            algorithm,
+            purpose,
+            encoding,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_public_key_attribute() {
+        let cases = [
+            (
+                "did/pub/Secp256k1/veriKey/hex",
+                KeyAlgorithm::Secp256k1,
+                KeyPurpose::VeriKey,
+                KeyEncoding::Hex,
+            ),
+            (
+                "did/pub/Ed25519/sigAuth/base58",
+                KeyAlgorithm::Ed25519,
+                KeyPurpose::SigAuth,
+                KeyEncoding::Base58,
+            ),
+            (
+                "did/pub/X25519/enc/base64",
+                KeyAlgorithm::X25519,
+                KeyPurpose::Enc,
+                KeyEncoding::Base64,
+            ),
+            (
+                "did/pub/RSA/veriKey/multibase",
+                KeyAlgorithm::Rsa,
+                KeyPurpose::VeriKey,
+                KeyEncoding::Multibase,
+            ),
+        ];
+        for (name, algorithm, purpose, encoding) in cases {
+            let attribute = name.parse::<PublicKeyAttribute>().unwrap();
+            assert_eq!(attribute.algorithm, algorithm, "{name}");
+            assert_eq!(attribute.purpose, purpose, "{name}");
+            assert_eq!(attribute.encoding, encoding, "{name}");
+            assert_eq!(attribute.to_string(), name);
+        }
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let attribute = "did/pub/Ed25519".parse::<PublicKeyAttribute>().unwrap();
+        assert_eq!(attribute.purpose, KeyPurpose::VeriKey);
+        assert_eq!(attribute.encoding, KeyEncoding::Hex);
+        let attribute = "did/pub/Ed25519/sigAuth"
+            .parse::<PublicKeyAttribute>()
+            .unwrap();
+        assert_eq!(attribute.purpose, KeyPurpose::SigAuth);
+        assert_eq!(attribute.encoding, KeyEncoding::Hex);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for name in [
+            "did/svc/MessagingService",
+            "did/pub",
+            "did/pub/P256/veriKey/hex",
+            "did/pub/Ed25519/signing/hex",
+            "did/pub/Ed25519/veriKey/pem",
+            "did/pub/Ed25519/veriKey/hex/extra",
+        ] {
+            assert!(
+                matches!(
+                    name.parse::<PublicKeyAttribute>(),
+                    Err(DidEthError::InvalidAttributeName(invalid)) if invalid == name
+                ),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_public_key() {
+        let key = [0x01, 0x02, 0xfe, 0xff];
+        assert_eq!(
+            PublicKey::encode(KeyEncoding::Hex, &key),
+            PublicKey::Hex("0102feff".to_string())
+        );
+        assert_eq!(
+            PublicKey::encode(KeyEncoding::Base58, &key),
+            PublicKey::Base58("2Vzei".to_string())
+        );
+        assert_eq!(
+            PublicKey::encode(KeyEncoding::Base64, &key),
+            PublicKey::Base64("AQL+/w==".to_string())
+        );
+        assert_eq!(
+            PublicKey::encode(KeyEncoding::Multibase, &key),
+            PublicKey::Multibase("z2Vzei".to_string())
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use ethers::types::{Address, U256};
+
+use crate::{
+    DelegateType, Did, DidEthError, DidEthRegistry, HistoryEntry, RegistryReceipt,
+    SignedMetaTransaction, Signing,
+};
+
+/// The ERC-1056 registry operations used by `dideth` consumers
+///
+/// Implemented by `DidEthRegistry` for a deployed registry and by `InMemoryRegistry`
+/// for tests that run without a chain.  Direct writes change the sender's identity.
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait RegistryBackend: Send + Sync {
+    /// the chain DIDs must be on
+    fn chain_id(&self) -> u64;
+
+    /// the account transactions are sent from
+    fn sender(&self) -> Address;
+
+    /// the owner of `did`, the zero address once it is deactivated
+    async fn owner(&self, did: &Did) -> Result<String, DidEthError>;
+
+    async fn is_deactivated(&self, did: &Did) -> Result<bool, DidEthError>;
+
+    async fn valid_delegate(
+        &self,
+        did: &Did,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<bool, DidEthError>;
+
+    /// the registry nonce for meta transactions on `did`, which is tracked per owner
+    async fn nonce(&self, did: &Did) -> Result<U256, DidEthError>;
+
+    /// attributes of `did` that are valid at the latest block
+    async fn attribute(&self, did: &Did) -> Result<Vec<(String, String)>, DidEthError>;
+
+    /// delegates of `did` that are valid at the latest block
+    async fn delegates(&self, did: &Did) -> Result<Vec<(DelegateType, Address)>, DidEthError>;
+
+    /// every registry event for `did`, oldest first
+    async fn history(&self, did: &Did) -> Result<Vec<HistoryEntry>, DidEthError>;
+
+    async fn set_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError>;
+
+    async fn revoke_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError>;
+
+    async fn add_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+        validity: u64,
+    ) -> Result<RegistryReceipt, DidEthError>;
+
+    async fn revoke_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<RegistryReceipt, DidEthError>;
+
+    /// transfer control of the identity to a new owner, the sender must be the current owner
+    async fn change_owner(
+        &self,
+        did: &Did,
+        new_owner: String,
+    ) -> Result<RegistryReceipt, DidEthError>;
+
+    /// permanently deactivate the identity by transferring ownership to the zero address
+    async fn deactivate(&self, did: &Did) -> Result<RegistryReceipt, DidEthError>;
+
+    /// relay a meta transaction signed by the identity owner
+    async fn submit_signed(
+        &self,
+        signed: &SignedMetaTransaction,
+    ) -> Result<RegistryReceipt, DidEthError>;
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<M: Signing + 'static> RegistryBackend for DidEthRegistry<M> {
+    fn chain_id(&self) -> u64 {
+        self.resolver().chain_id()
+    }
+
+    fn sender(&self) -> Address {
+        DidEthRegistry::sender(self)
+    }
+
+    async fn owner(&self, did: &Did) -> Result<String, DidEthError> {
+        DidEthRegistry::owner(self, did).await
+    }
+
+    async fn is_deactivated(&self, did: &Did) -> Result<bool, DidEthError> {
+        self.resolver().is_deactivated(did).await
+    }
+
+    async fn valid_delegate(
+        &self,
+        did: &Did,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<bool, DidEthError> {
+        DidEthRegistry::valid_delegate(self, did, delegate_type, delegate).await
+    }
+
+    async fn nonce(&self, did: &Did) -> Result<U256, DidEthError> {
+        DidEthRegistry::nonce(self, did).await
+    }
+
+    async fn attribute(&self, did: &Did) -> Result<Vec<(String, String)>, DidEthError> {
+        DidEthRegistry::attribute(self, did).await
+    }
+
+    async fn delegates(&self, did: &Did) -> Result<Vec<(DelegateType, Address)>, DidEthError> {
+        self.resolver().delegates(did).await
+    }
+
+    async fn history(&self, did: &Did) -> Result<Vec<HistoryEntry>, DidEthError> {
+        self.resolver().history(did).await
+    }
+
+    async fn set_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::set_attribute(self, name, value).await
+    }
+
+    async fn revoke_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::revoke_attribute(self, name, value).await
+    }
+
+    async fn add_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+        validity: u64,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::add_delegate(self, delegate_type, delegate, validity).await
+    }
+
+    async fn revoke_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::revoke_delegate(self, delegate_type, delegate).await
+    }
+
+    async fn change_owner(
+        &self,
+        did: &Did,
+        new_owner: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::change_owner(self, did, new_owner).await
+    }
+
+    async fn deactivate(&self, did: &Did) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::deactivate(self, did).await
+    }
+
+    async fn submit_signed(
+        &self,
+        signed: &SignedMetaTransaction,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        DidEthRegistry::submit_signed(self, signed).await
+    }
+}
@@ -0,0 +1,385 @@
+use ethers::providers::Middleware;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::PathBuf};
+
+use crate::{Did, ResolutionOptions, ResolutionResult, Resolver};
+
+/// A resolved document stored under the `changed(identity)` block it was resolved at
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedResolution {
+    /// `changed(identity)` when the document was resolved
+    pub changed: u64,
+    /// the block timestamp validity periods were compared against
+    pub timestamp: u64,
+    /// the earliest `validTo` of the changes valid at `timestamp`
+    pub expires: Option<u64>,
+    /// unix time the entry was stored
+    pub stored_at: u64,
+    pub result: ResolutionResult,
+}
+
+impl CachedResolution {
+    /// whether the entry still answers for `changed`, chain time is estimated from the
+    /// time elapsed since the entry was stored
+    pub fn is_current(&self, changed: u64, unix_time: u64) -> bool {
+        let elapsed = unix_time.saturating_sub(self.stored_at);
+        self.changed == changed
+            && self
+                .expires
+                .is_none_or(|expires| self.timestamp + elapsed <= expires)
+    }
+}
+
+/// Storage for cached resolutions
+///
+/// Entries are keyed on the DID exactly as it was requested, since the document `id`
+/// keeps that spelling.  Every spelling and form of one identity has its own entry.
+pub trait ResolutionCache: Send + Sync {
+    fn get(&self, did: &str) -> Option<CachedResolution>;
+
+    fn put(&self, did: &str, entry: CachedResolution);
+
+    /// drop the entry for `did`
+    fn invalidate(&self, did: &str);
+
+    /// drop every entry
+    fn clear(&self);
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug)]
+struct Slot {
+    inserted: Instant,
+    last_used: u64,
+    entry: CachedResolution,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    slots: HashMap<String, Slot>,
+    clock: u64,
+}
+
+/// An in-memory cache holding at most `capacity` entries for at most `ttl`,
+/// the least recently used entry is evicted first
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            state: Mutex::new(MemoryState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().expect("cache state")
+    }
+}
+
+impl ResolutionCache for MemoryCache {
+    fn get(&self, did: &str) -> Option<CachedResolution> {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+        match state.slots.get_mut(did) {
+            Some(slot) if slot.inserted.elapsed() <= self.ttl => {
+                slot.last_used = clock;
+                Some(slot.entry.clone())
+            }
+            Some(_) => {
+                state.slots.remove(did);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, did: &str, entry: CachedResolution) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        state.clock += 1;
+        let slot = Slot {
+            inserted: Instant::now(),
+            last_used: state.clock,
+            entry,
+        };
+        state.slots.insert(did.to_string(), slot);
+        while state.slots.len() > self.capacity {
+            let oldest = state
+                .slots
+                .iter()
+                .min_by_key(|(_, slot)| slot.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.slots.remove(&oldest);
+            }
+        }
+    }
+
+    fn invalidate(&self, did: &str) {
+        self.lock().slots.remove(did);
+    }
+
+    fn clear(&self) {
+        self.lock().slots.clear();
+    }
+}
+
+/// A cache of one JSON file per identity in a directory, shared between runs
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DiskCache {
+    /// the directory is created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// the file name is the hex encoded DID, which keeps spellings that differ only in
+    /// case apart on case insensitive file systems
+    fn path(&self, did: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(did)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ResolutionCache for DiskCache {
+    fn get(&self, did: &str) -> Option<CachedResolution> {
+        let contents = fs::read(self.path(did)).ok()?;
+        match serde_json::from_slice(&contents) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                tracing::warn!("ignoring unreadable cache entry for {did}: {err}");
+                None
+            }
+        }
+    }
+
+    fn put(&self, did: &str, entry: CachedResolution) {
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| Ok(serde_json::to_vec(&entry)?))
+            .and_then(|contents| fs::write(self.path(did), contents));
+        if let Err(err) = written {
+            tracing::warn!("failed to cache the resolution of {did}: {err}");
+        }
+    }
+
+    fn invalidate(&self, did: &str) {
+        let _ = fs::remove_file(self.path(did));
+    }
+
+    fn clear(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// A `Resolver` that reuses documents while the identity's `changed` block is unchanged
+///
+/// A repeat resolution of the latest version costs a single `changed` call.  Entries
+/// are also refreshed once an attribute or delegate in the document may have expired.
+/// Historical versions and failed resolutions are not cached.
+pub struct CachingResolver<M, C> {
+    resolver: Resolver<M>,
+    cache: C,
+}
+
+impl<M: Middleware + 'static, C: ResolutionCache> CachingResolver<M, C> {
+    pub fn new(resolver: Resolver<M>, cache: C) -> Self {
+        Self { resolver, cache }
+    }
+
+    pub fn resolver(&self) -> &Resolver<M> {
+        &self.resolver
+    }
+
+    pub fn cache(&self) -> &C {
+        &self.cache
+    }
+
+    /// drop the cached resolution of `did`
+    pub fn invalidate(&self, did: &str) {
+        self.cache.invalidate(did);
+    }
+
+    /// resolve `did` like `Resolver::resolve`, answering from the cache when possible
+    pub async fn resolve(&self, did: &str, options: &ResolutionOptions) -> ResolutionResult {
+        if !options.is_latest() {
+            return self.resolver.resolve(did, options).await;
+        }
+        let Ok(parsed) = did.parse::<Did>() else {
+            return self.resolver.resolve(did, options).await;
+        };
+        let changed = match self.resolver.changed(&parsed).await {
+            Ok(changed) => changed,
+            Err(_) => return self.resolver.resolve(did, options).await,
+        };
+        if let Some(entry) = self.cache.get(did) {
+            if entry.is_current(changed, unix_time()) {
+                tracing::debug!("{did} unchanged since block {changed}");
+                return entry.result;
+            }
+        }
+        match self.resolver.resolve_did(did, options).await {
+            Ok(resolved) => {
+                let entry = CachedResolution {
+                    changed,
+                    timestamp: resolved.timestamp.low_u64(),
+                    expires: resolved.expires.map(|expires| expires.low_u64()),
+                    stored_at: unix_time(),
+                    result: resolved.result,
+                };
+                self.cache.put(did, entry.clone());
+                entry.result
+            }
+            Err(err) => {
+                tracing::warn!("failed to resolve {did}: {err}");
+                ResolutionResult::error(&err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DidDocumentMetadata, DidResolutionMetadata, SEPOLIA_CHAIN_ID};
+    use ethers::types::Address;
+
+    fn did(byte: u8) -> String {
+        Did::new(SEPOLIA_CHAIN_ID, Address::repeat_byte(byte)).to_string()
+    }
+
+    fn entry(changed: u64) -> CachedResolution {
+        CachedResolution {
+            changed,
+            timestamp: 1_000,
+            expires: Some(1_100),
+            stored_at: 5_000,
+            result: ResolutionResult {
+                did_document: None,
+                did_resolution_metadata: DidResolutionMetadata::default(),
+                did_document_metadata: DidDocumentMetadata::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_is_current() {
+        let entry = entry(7);
+        assert!(entry.is_current(7, 5_000));
+        assert!(entry.is_current(7, 5_100));
+        assert!(!entry.is_current(7, 5_101));
+        assert!(!entry.is_current(8, 5_000));
+        let unexpiring = CachedResolution {
+            expires: None,
+            ..entry
+        };
+        assert!(unexpiring.is_current(7, u64::MAX));
+    }
+
+    #[test]
+    fn test_spellings_are_kept_apart() {
+        let did = did(0xab);
+        let lowercase = did.to_lowercase();
+        assert_ne!(lowercase, did);
+        let cache = MemoryCache::new(2, Duration::from_secs(60));
+        cache.put(&did, entry(1));
+        assert!(cache.get(&lowercase).is_none());
+        cache.put(&lowercase, entry(2));
+        assert_eq!(cache.get(&did).unwrap().changed, 1);
+        assert_eq!(cache.get(&lowercase).unwrap().changed, 2);
+    }
+
+    #[test]
+    fn test_memory_cache_lru() {
+        let cache = MemoryCache::new(2, Duration::from_secs(60));
+        cache.put(&did(1), entry(1));
+        cache.put(&did(2), entry(2));
+        // reading 1 makes 2 the least recently used
+        assert_eq!(cache.get(&did(1)).unwrap().changed, 1);
+        cache.put(&did(3), entry(3));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&did(2)).is_none());
+        assert!(cache.get(&did(1)).is_some());
+        assert!(cache.get(&did(3)).is_some());
+
+        cache.invalidate(&did(1));
+        assert!(cache.get(&did(1)).is_none());
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_memory_cache_ttl() {
+        let cache = MemoryCache::new(2, Duration::ZERO);
+        cache.put(&did(1), entry(1));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(cache.get(&did(1)).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("dideth-cache-{}", std::process::id()));
+        let cache = DiskCache::new(&dir);
+        assert!(cache.get(&did(1)).is_none());
+        cache.put(&did(1), entry(1));
+        cache.put(&did(2), entry(2));
+        assert_eq!(cache.get(&did(1)), Some(entry(1)));
+        let lowercase = did(0xab).to_lowercase();
+        cache.put(&did(0xab), entry(3));
+        assert!(cache.get(&lowercase).is_none());
+        // entries are shared between instances
+        assert_eq!(DiskCache::new(&dir).get(&did(2)), Some(entry(2)));
+
+        cache.invalidate(&did(1));
+        assert!(cache.get(&did(1)).is_none());
+        cache.clear();
+        assert!(cache.get(&did(2)).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+use async_trait::async_trait;
+use ethers::providers::{JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// Errors from recording and replaying transports
+#[derive(Debug, thiserror::Error)]
+pub enum CassetteError {
+    #[error(transparent)]
+    JsonRpc(#[from] JsonRpcError),
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    #[error("no recorded response for {method} {params}")]
+    Unrecorded { method: String, params: Value },
+}
+
+impl RpcError for CassetteError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            CassetteError::JsonRpc(err) => Some(err),
+            CassetteError::Provider(err) => err.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            CassetteError::Serde(err) => Some(err),
+            CassetteError::Provider(err) => err.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<CassetteError> for ProviderError {
+    fn from(err: CassetteError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+/// A JSON-RPC error response as it is stored in a cassette
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl From<&JsonRpcError> for RecordedError {
+    fn from(err: &JsonRpcError) -> Self {
+        Self {
+            code: err.code,
+            message: err.message.clone(),
+            data: err.data.clone(),
+        }
+    }
+}
+
+impl From<RecordedError> for JsonRpcError {
+    fn from(err: RecordedError) -> Self {
+        Self {
+            code: err.code,
+            message: err.message,
+            data: err.data,
+        }
+    }
+}
+
+/// The node's answer to a recorded request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Response {
+    Result(Value),
+    Error(RecordedError),
+}
+
+/// One recorded JSON-RPC request and its response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub params: Value,
+    #[serde(flatten)]
+    pub response: Response,
+}
+
+/// JSON-RPC interactions in the order they were made
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+}
+
+/// A transport that records every request and response made through `inner`
+///
+/// Clones share the recording, keep one to save the cassette once the
+/// provider is done.  Transport failures without a response are not recorded.
+#[derive(Debug, Clone)]
+pub struct RecordingTransport<T> {
+    inner: T,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl<T> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+
+    /// the interactions recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().expect("cassette").clone()
+    }
+
+    fn record(&self, method: &str, params: Value, response: Response) {
+        self.cassette
+            .lock()
+            .expect("cassette")
+            .interactions
+            .push(Interaction {
+                method: method.to_string(),
+                params,
+                response,
+            });
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T: JsonRpcClient> JsonRpcClient for RecordingTransport<T> {
+    type Error = CassetteError;
+
+    async fn request<P, R>(&self, method: &str, params: P) -> Result<R, Self::Error>
+    where
+        P: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let recorded_params = serde_json::to_value(&params)?;
+        match self.inner.request::<P, Value>(method, params).await {
+            Ok(result) => {
+                self.record(method, recorded_params, Response::Result(result.clone()));
+                Ok(serde_json::from_value(result)?)
+            }
+            Err(err) => match err.as_error_response() {
+                Some(response) => {
+                    self.record(method, recorded_params, Response::Error(response.into()));
+                    Err(CassetteError::JsonRpc(response.clone()))
+                }
+                None => Err(CassetteError::Provider(err.into())),
+            },
+        }
+    }
+}
+
+/// A transport that answers from a cassette without any network access
+///
+/// Requests are matched by method and params.  Recorded responses to the same
+/// request are served in order, the last one is repeated once they run out.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    interactions: Vec<Interaction>,
+    served: Mutex<Vec<bool>>,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        let served = vec![false; cassette.interactions.len()];
+        Self {
+            interactions: cassette.interactions,
+            served: Mutex::new(served),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    fn response(&self, method: &str, params: &Value) -> Option<Response> {
+        let mut served = self.served.lock().expect("replay state");
+        let matching = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| {
+                interaction.method == method && interaction.params == *params
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let index = matching
+            .iter()
+            .find(|index| !served[**index])
+            .or(matching.last())
+            .copied()?;
+        served[index] = true;
+        Some(self.interactions[index].response.clone())
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl JsonRpcClient for ReplayTransport {
+    type Error = CassetteError;
+
+    async fn request<P, R>(&self, method: &str, params: P) -> Result<R, Self::Error>
+    where
+        P: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(&params)?;
+        match self.response(method, &params) {
+            Some(Response::Result(result)) => Ok(serde_json::from_value(result)?),
+            Some(Response::Error(err)) => Err(CassetteError::JsonRpc(err.into())),
+            None => Err(CassetteError::Unrecorded {
+                method: method.to_string(),
+                params,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{Middleware, MockProvider, MockResponse, Provider},
+        types::U64,
+    };
+
+    async fn recorded() -> Cassette {
+        let mock = MockProvider::new();
+        mock.push::<U64, U64>(U64::from(7)).unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "header not found".to_string(),
+            data: None,
+        }));
+        mock.push::<U64, U64>(U64::from(5)).unwrap();
+        let recorder = RecordingTransport::new(mock);
+        let provider = Provider::new(recorder.clone());
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(5));
+        assert!(provider.get_block_number().await.is_err());
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(7));
+        recorder.cassette()
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let cassette = recorded().await;
+        assert_eq!(cassette.interactions.len(), 3);
+        assert!(cassette
+            .interactions
+            .iter()
+            .all(|interaction| interaction.method == "eth_blockNumber"));
+        assert_eq!(
+            cassette.interactions[0].response,
+            Response::Result(Value::String("0x5".to_string()))
+        );
+        assert!(matches!(
+            &cassette.interactions[1].response,
+            Response::Error(err) if err.message == "header not found"
+        ));
+
+        let json = serde_json::to_value(&cassette).unwrap();
+        assert_eq!(json["interactions"][0]["result"], "0x5");
+        assert_eq!(json["interactions"][1]["error"]["code"], -32000);
+        let roundtrip: Cassette = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip, cassette);
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let cassette = recorded().await;
+        let provider = Provider::new(ReplayTransport::new(cassette));
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(5));
+        let err = provider.get_block_number().await.unwrap_err();
+        assert_eq!(
+            err.as_error_response().map(|err| err.message.as_str()),
+            Some("header not found")
+        );
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(7));
+        // the last response is repeated once the recording runs out
+        assert_eq!(provider.get_block_number().await.unwrap(), U64::from(7));
+        assert!(provider.get_chainid().await.is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use ethers::types::{Address, U256};
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::{
+    error::parse_address, DidEthError, CONFIRMATION_TIMEOUT, DATA_LIFETIME, DID_ETH_REGISTRY,
+    LOG_RANGE, REQUIRED_CONFIRMATIONS,
+};
+
+pub const MAINNET_CHAIN_ID: u64 = 1;
+pub const SEPOLIA_CHAIN_ID: u64 = 11155111;
+pub const DEV_CHAIN_ID: u64 = 31337;
+
+/// Well known networks with a deployed ERC-1056 registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Mainnet,
+    Sepolia,
+    /// local anvil or hardhat node
+    Dev,
+}
+
+impl Network {
+    pub const ALL: [Network; 3] = [Network::Mainnet, Network::Sepolia, Network::Dev];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Sepolia => "sepolia",
+            Network::Dev => "dev",
+        }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Network::Mainnet => MAINNET_CHAIN_ID,
+            Network::Sepolia => SEPOLIA_CHAIN_ID,
+            Network::Dev => DEV_CHAIN_ID,
+        }
+    }
+
+    pub fn registry(&self) -> Address {
+        let address = match self {
+            Network::Mainnet => "0xdca7ef03e98e0dc2b855be647c39abe984fcf21b",
+            Network::Sepolia => DID_ETH_REGISTRY,
+            // first contract deployed by the default dev account
+            Network::Dev => "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+        };
+        address.parse().expect("well known registry address")
+    }
+
+    pub fn from_chain_id(chain_id: u64) -> Option<Network> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.chain_id() == chain_id)
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Network {
+    type Err = DidEthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.name() == s)
+            .ok_or_else(|| DidEthError::UnknownNetwork(s.to_string()))
+    }
+}
+
+/// Connection settings for a `DidEthRegistry`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryConfig {
+    pub registry: Address,
+    /// when set, the connected chain must report this chain id
+    pub chain_id: Option<u64>,
+    pub confirmations: usize,
+    /// how long to wait for the required confirmations of a transaction
+    pub confirmation_timeout: Duration,
+    /// validity in seconds of newly set attributes
+    pub attribute_lifetime: u64,
+    /// blocks per `eth_getLogs` request when reading an identity's history
+    pub log_range: u64,
+}
+
+/// the did:eth registry on sepolia
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self::for_network(Network::Sepolia)
+    }
+}
+
+impl RegistryConfig {
+    pub fn for_network(network: Network) -> Self {
+        Self {
+            registry: network.registry(),
+            chain_id: Some(network.chain_id()),
+            confirmations: REQUIRED_CONFIRMATIONS,
+            confirmation_timeout: CONFIRMATION_TIMEOUT,
+            attribute_lifetime: DATA_LIFETIME,
+            log_range: LOG_RANGE,
+        }
+    }
+
+    /// build a config from optional network name and registry address settings,
+    /// the registry address overrides the network's well known deployment
+    pub fn from_settings(
+        network: Option<&str>,
+        registry: Option<&str>,
+    ) -> Result<Self, DidEthError> {
+        let config = match network {
+            Some(network) => Self::for_network(network.parse()?),
+            None => Self::default(),
+        };
+        match registry {
+            Some(registry) => Ok(config.with_registry(parse_address(registry)?)),
+            None => Ok(config),
+        }
+    }
+
+    pub fn with_registry(mut self, registry: Address) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    pub fn with_confirmation_timeout(mut self, confirmation_timeout: Duration) -> Self {
+        self.confirmation_timeout = confirmation_timeout;
+        self
+    }
+
+    pub fn with_attribute_lifetime(mut self, attribute_lifetime: u64) -> Self {
+        self.attribute_lifetime = attribute_lifetime;
+        self
+    }
+
+    pub fn with_log_range(mut self, log_range: u64) -> Self {
+        self.log_range = log_range;
+        self
+    }
+
+    pub fn validate_chain_id(&self, connected: U256) -> Result<(), DidEthError> {
+        match self.chain_id {
+            Some(expected) if connected != U256::from(expected) => {
+                Err(DidEthError::ChainMismatch {
+                    connected,
+                    expected,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = RegistryConfig::default();
+        assert_eq!(config.registry, DID_ETH_REGISTRY.parse().unwrap());
+        assert_eq!(config.chain_id, Some(SEPOLIA_CHAIN_ID));
+        assert_eq!(config.confirmations, REQUIRED_CONFIRMATIONS);
+        assert_eq!(config.attribute_lifetime, DATA_LIFETIME);
+        assert_eq!(config.log_range, LOG_RANGE);
+        assert!(config
+            .validate_chain_id(U256::from(SEPOLIA_CHAIN_ID))
+            .is_ok());
+        assert!(matches!(
+            config.validate_chain_id(U256::from(MAINNET_CHAIN_ID)),
+            Err(DidEthError::ChainMismatch { .. })
+        ));
+        // without a chain id any chain is accepted
+        let config = RegistryConfig {
+            chain_id: None,
+            ..config
+        };
+        assert!(config.validate_chain_id(U256::from(5)).is_ok());
+    }
+
+    #[test]
+    fn test_network_config() {
+        let config = RegistryConfig::for_network(Network::Sepolia).with_confirmations(1);
+        assert_eq!(config.registry, DID_ETH_REGISTRY.parse().unwrap());
+        assert_eq!(config.chain_id, Some(SEPOLIA_CHAIN_ID));
+        assert_eq!(config.confirmations, 1);
+        assert!(config
+            .validate_chain_id(U256::from(SEPOLIA_CHAIN_ID))
+            .is_ok());
+        assert!(matches!(
+            config.validate_chain_id(U256::from(MAINNET_CHAIN_ID)),
+            Err(DidEthError::ChainMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_network_lookup() {
+        for network in Network::ALL {
+            assert_eq!(network.name().parse::<Network>().unwrap(), network);
+            assert_eq!(Network::from_chain_id(network.chain_id()), Some(network));
+        }
+        assert!("goerli".parse::<Network>().is_err());
+        assert_eq!(Network::from_chain_id(5), None);
+    }
+
+    #[test]
+    fn test_from_settings() {
+        let registry = "0x0000000000000000000000000000000000001056";
+        let config = RegistryConfig::from_settings(Some("dev"), Some(registry)).unwrap();
+        assert_eq!(config.registry, registry.parse().unwrap());
+        assert_eq!(config.chain_id, Some(DEV_CHAIN_ID));
+        assert_eq!(
+            RegistryConfig::from_settings(None, None).unwrap(),
+            RegistryConfig::default()
+        );
+        assert!(matches!(
+            RegistryConfig::from_settings(Some("goerli"), None),
+            Err(DidEthError::UnknownNetwork(_))
+        ));
+        assert!(matches!(
+            RegistryConfig::from_settings(None, Some("0x1234")),
+            Err(DidEthError::InvalidAddress(_))
+        ));
+    }
+}
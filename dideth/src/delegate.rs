@@ -0,0 +1,95 @@
+use std::{fmt, str::FromStr};
+
+use crate::DidEthError;
+
+/// Delegate types recognized by did:ethr resolvers
+///
+/// `VeriKey` delegates may sign on behalf of the identity, `SigAuth` delegates
+/// may additionally authenticate as the identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DelegateType {
+    #[serde(rename = "veriKey")]
+    VeriKey,
+    #[serde(rename = "sigAuth")]
+    SigAuth,
+}
+
+impl DelegateType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DelegateType::VeriKey => "veriKey",
+            DelegateType::SigAuth => "sigAuth",
+        }
+    }
+
+    /// the registry stores delegate types as utf8 right padded with zeros
+    pub fn to_bytes32(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        let name = self.as_str().as_bytes();
+        bytes[..name.len()].copy_from_slice(name);
+        bytes
+    }
+
+    pub fn from_bytes32(bytes: &[u8; 32]) -> Result<Self, DidEthError> {
+        let name = bytes
+            .iter()
+            .take_while(|b| **b != 0)
+            .map(|b| *b as char)
+            .collect::<String>();
+        name.parse()
+    }
+}
+
+impl fmt::Display for DelegateType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DelegateType {
+    type Err = DidEthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "veriKey" => Ok(DelegateType::VeriKey),
+            "sigAuth" => Ok(DelegateType::SigAuth),
+            _ => Err(DidEthError::UnknownDelegateType(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::format_bytes32_string;
+
+    #[test]
+    fn test_delegate_type_bytes32() {
+        assert_eq!(
+            DelegateType::VeriKey.to_bytes32(),
+            format_bytes32_string("veriKey").unwrap()
+        );
+        assert_eq!(
+            DelegateType::SigAuth.to_bytes32(),
+            format_bytes32_string("sigAuth").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_delegate_type_roundtrip() {
+        for delegate_type in [DelegateType::VeriKey, DelegateType::SigAuth] {
+            let bytes = delegate_type.to_bytes32();
+            assert_eq!(DelegateType::from_bytes32(&bytes).unwrap(), delegate_type);
+            assert_eq!(
+                delegate_type.to_string().parse::<DelegateType>().unwrap(),
+                delegate_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_delegate_type_unknown() {
+        assert!("enc".parse::<DelegateType>().is_err());
+        assert!(DelegateType::from_bytes32(&format_bytes32_string("veriKeyX").unwrap()).is_err());
+    }
+}
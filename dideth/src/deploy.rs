@@ -0,0 +1,89 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, U64},
+};
+use std::sync::Arc;
+
+use crate::{
+    within, DIDRegistry, DidEthError, RegistryConfig, Signing, DIDREGISTRY_DEPLOYED_BYTECODE,
+};
+
+/// deploy the bundled DIDRegistry with the client's signer and point the config at it
+///
+/// The deployed code is checked against the artifact's `deployedBytecode` before the
+/// address is written into the returned config.
+pub async fn deploy_registry<M: Signing + 'static>(
+    client: Arc<M>,
+    config: RegistryConfig,
+) -> Result<RegistryConfig, DidEthError> {
+    let chain_id = client.get_chainid().await.map_err(DidEthError::transport)?;
+    config.validate_chain_id(chain_id)?;
+
+    let deployer = DIDRegistry::deploy(client.clone(), ())?;
+    let pending = client
+        .send_transaction(deployer.deployer.tx, None)
+        .await
+        .map_err(DidEthError::transport)?;
+    let tx_hash = pending.tx_hash();
+    tracing::info!("Deployment sent: {tx_hash:#x}");
+    let receipt = within(
+        config.confirmation_timeout,
+        pending.confirmations(config.confirmations),
+    )
+    .await
+    .ok_or(DidEthError::ConfirmationTimeout(tx_hash))?
+    .map_err(DidEthError::transport)?
+    .ok_or(DidEthError::TransactionDropped(tx_hash))?;
+    let registry = match (receipt.status, receipt.contract_address) {
+        (Some(status), Some(registry)) if status == U64::one() => registry,
+        _ => return Err(DidEthError::TransactionFailed(tx_hash)),
+    };
+
+    verify_registry(client.as_ref(), registry).await?;
+    tracing::info!("DIDRegistry deployed at {registry:#x}");
+    Ok(config.with_registry(registry))
+}
+
+/// check that the code at `registry` is the bundled DIDRegistry
+pub async fn verify_registry<M: Middleware + 'static>(
+    client: &M,
+    registry: Address,
+) -> Result<(), DidEthError> {
+    let code = client
+        .get_code(registry, None)
+        .await
+        .map_err(DidEthError::transport)?;
+    if code != *DIDREGISTRY_DEPLOYED_BYTECODE {
+        return Err(DidEthError::RegistryCodeMismatch(registry));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{MockProvider, Provider},
+        types::Bytes,
+    };
+
+    #[tokio::test]
+    async fn test_verify_registry() {
+        let (provider, mock) = Provider::mocked();
+        let registry = Address::random();
+        mock.push::<Bytes, Bytes>(DIDREGISTRY_DEPLOYED_BYTECODE.clone())
+            .unwrap();
+        assert!(verify_registry(&provider, registry).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_verify_registry_mismatch() {
+        let (provider, mock): (Provider<MockProvider>, _) = Provider::mocked();
+        let registry = Address::random();
+        mock.push::<Bytes, Bytes>(Bytes::default()).unwrap();
+        assert!(matches!(
+            verify_registry(&provider, registry).await,
+            Err(DidEthError::RegistryCodeMismatch(address)) if address == registry
+        ));
+    }
+}
@@ -0,0 +1,411 @@
+use ethers::{
+    core::k256::{elliptic_curve::sec1::ToEncodedPoint, PublicKey},
+    types::Address,
+    utils::{keccak256, to_checksum},
+};
+use std::{fmt, str::FromStr};
+
+use crate::{Network, MAINNET_CHAIN_ID};
+
+/// Reasons a did:ethr DID fails to parse
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DidParseError {
+    #[error("{0} is not a did:ethr DID, expected did:ethr:[network:]identifier")]
+    InvalidFormat(String),
+    #[error("unsupported DID method {0}, expected ethr")]
+    UnsupportedMethod(String),
+    #[error("unknown network {0}")]
+    UnknownNetwork(String),
+    #[error("invalid chain id {0}")]
+    InvalidChainId(String),
+    #[error("{0} is neither an address nor a compressed public key")]
+    InvalidIdentifier(String),
+    #[error("{0} has an invalid EIP-55 checksum")]
+    InvalidChecksum(String),
+    #[error("{0} is not a valid secp256k1 public key")]
+    InvalidPublicKey(String),
+}
+
+/// The network segment of a did:ethr DID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DidNetwork {
+    Named(Network),
+    /// `0x` prefixed hex chain id
+    ChainId(u64),
+}
+
+impl DidNetwork {
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            DidNetwork::Named(network) => network.chain_id(),
+            DidNetwork::ChainId(chain_id) => *chain_id,
+        }
+    }
+}
+
+impl fmt::Display for DidNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DidNetwork::Named(network) => write!(f, "{network}"),
+            DidNetwork::ChainId(chain_id) => write!(f, "{chain_id:#x}"),
+        }
+    }
+}
+
+impl FromStr for DidNetwork {
+    type Err = DidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("0x") {
+            Some(chain_id) => u64::from_str_radix(chain_id, 16)
+                .map(DidNetwork::ChainId)
+                .map_err(|_| DidParseError::InvalidChainId(s.to_string())),
+            None => s
+                .parse()
+                .map(DidNetwork::Named)
+                .map_err(|_| DidParseError::UnknownNetwork(s.to_string())),
+        }
+    }
+}
+
+/// A compressed secp256k1 public key, only constructed from a valid curve point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompressedPublicKey {
+    bytes: [u8; 33],
+    address: Address,
+}
+
+impl CompressedPublicKey {
+    /// `None` unless `bytes` is a 33 byte compressed point on the curve
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: [u8; 33] = bytes.try_into().ok()?;
+        let key = PublicKey::from_sec1_bytes(&bytes).ok()?;
+        let uncompressed = key.to_encoded_point(false);
+        let address = Address::from_slice(&keccak256(&uncompressed.as_bytes()[1..])[12..]);
+        Some(Self { bytes, address })
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 33] {
+        &self.bytes
+    }
+
+    /// the Ethereum address of the key
+    pub fn address(&self) -> Address {
+        self.address
+    }
+}
+
+/// The identity a did:ethr DID refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    Address(Address),
+    PublicKey(CompressedPublicKey),
+}
+
+impl Identifier {
+    /// the registry identity, public keys are converted to their Ethereum address
+    pub fn address(&self) -> Address {
+        match self {
+            Identifier::Address(address) => *address,
+            Identifier::PublicKey(key) => key.address(),
+        }
+    }
+}
+
+impl From<Address> for Identifier {
+    fn from(address: Address) -> Self {
+        Identifier::Address(address)
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Address(address) => f.write_str(&to_checksum(address, None)),
+            Identifier::PublicKey(key) => write!(f, "0x{}", hex::encode(key.as_bytes())),
+        }
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = DidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DidParseError::InvalidIdentifier(s.to_string());
+        let digits = s.strip_prefix("0x").ok_or_else(invalid)?;
+        let bytes = hex::decode(digits).map_err(|_| invalid())?;
+        match bytes.len() {
+            20 => {
+                let address = Address::from_slice(&bytes);
+                // mixed case addresses carry an EIP-55 checksum
+                let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase())
+                    && digits.chars().any(|c| c.is_ascii_uppercase());
+                if mixed_case && to_checksum(&address, None) != s {
+                    return Err(DidParseError::InvalidChecksum(s.to_string()));
+                }
+                Ok(Identifier::Address(address))
+            }
+            33 => CompressedPublicKey::from_bytes(&bytes)
+                .map(Identifier::PublicKey)
+                .ok_or_else(|| DidParseError::InvalidPublicKey(s.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A did:ethr DID, `did:ethr:[network:]identifier`
+///
+/// Without a network segment the DID refers to mainnet.  The network is either a
+/// well known network name or a `0x` prefixed hex chain id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Did {
+    network: Option<DidNetwork>,
+    identifier: Identifier,
+}
+
+impl Did {
+    /// the canonical DID of `identifier` on `chain_id`
+    pub fn new(chain_id: u64, identifier: impl Into<Identifier>) -> Self {
+        let network = match Network::from_chain_id(chain_id) {
+            Some(Network::Mainnet) => None,
+            Some(network) => Some(DidNetwork::Named(network)),
+            None => Some(DidNetwork::ChainId(chain_id)),
+        };
+        Self {
+            network,
+            identifier: identifier.into(),
+        }
+    }
+
+    /// parse a DID, or a bare address or public key which is placed on `chain_id`
+    pub fn parse_on_chain(input: &str, chain_id: u64) -> Result<Self, DidParseError> {
+        if input.starts_with("did:") {
+            input.parse()
+        } else {
+            Ok(Self::new(chain_id, input.parse::<Identifier>()?))
+        }
+    }
+
+    pub fn network(&self) -> Option<DidNetwork> {
+        self.network
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.network
+            .map(|network| network.chain_id())
+            .unwrap_or(MAINNET_CHAIN_ID)
+    }
+
+    pub fn identifier(&self) -> &Identifier {
+        &self.identifier
+    }
+
+    /// the identity address used for registry lookups
+    pub fn address(&self) -> Address {
+        self.identifier.address()
+    }
+}
+
+impl fmt::Display for Did {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.network {
+            Some(network) => write!(f, "did:ethr:{network}:{}", self.identifier),
+            None => write!(f, "did:ethr:{}", self.identifier),
+        }
+    }
+}
+
+impl FromStr for Did {
+    type Err = DidParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        match parts.as_slice() {
+            ["did", "ethr", identifier] => Ok(Self {
+                network: None,
+                identifier: identifier.parse()?,
+            }),
+            ["did", "ethr", network, identifier] => Ok(Self {
+                network: Some(network.parse()?),
+                identifier: identifier.parse()?,
+            }),
+            ["did", method, ..] if *method != "ethr" => {
+                Err(DidParseError::UnsupportedMethod(method.to_string()))
+            }
+            _ => Err(DidParseError::InvalidFormat(s.to_string())),
+        }
+    }
+}
+
+impl serde::Serialize for Did {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Did {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let did = String::deserialize(deserializer)?;
+        did.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DEV_CHAIN_ID, SEPOLIA_CHAIN_ID};
+
+    const ADDRESS: &str = "0xb9c5714089478a327f09197987f16f9e5d936e8a";
+    const CHECKSUMMED: &str = "0xB9C5714089478a327F09197987f16f9E5d936E8a";
+    // the compressed public key of the secret key 1
+    const PUBLIC_KEY: &str = "0x0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn test_parse_address_did() {
+        let did = format!("did:ethr:{CHECKSUMMED}").parse::<Did>().unwrap();
+        assert_eq!(did.network(), None);
+        assert_eq!(did.chain_id(), MAINNET_CHAIN_ID);
+        assert_eq!(did.address(), ADDRESS.parse().unwrap());
+        assert_eq!(did.to_string(), format!("did:ethr:{CHECKSUMMED}"));
+
+        let lower = format!("did:ethr:{ADDRESS}").parse::<Did>().unwrap();
+        assert_eq!(lower, did);
+        let upper = format!("did:ethr:0x{}", ADDRESS[2..].to_uppercase())
+            .parse::<Did>()
+            .unwrap();
+        assert_eq!(upper, did);
+    }
+
+    #[test]
+    fn test_parse_network_did() {
+        let did = format!("did:ethr:sepolia:{CHECKSUMMED}")
+            .parse::<Did>()
+            .unwrap();
+        assert_eq!(did.network(), Some(DidNetwork::Named(Network::Sepolia)));
+        assert_eq!(did.chain_id(), SEPOLIA_CHAIN_ID);
+        assert_eq!(did.to_string(), format!("did:ethr:sepolia:{CHECKSUMMED}"));
+
+        let did = format!("did:ethr:0x7a69:{CHECKSUMMED}")
+            .parse::<Did>()
+            .unwrap();
+        assert_eq!(did.network(), Some(DidNetwork::ChainId(DEV_CHAIN_ID)));
+        assert_eq!(did.chain_id(), DEV_CHAIN_ID);
+        assert_eq!(did.to_string(), format!("did:ethr:0x7a69:{CHECKSUMMED}"));
+    }
+
+    #[test]
+    fn test_canonical_did() {
+        let address = ADDRESS.parse::<Address>().unwrap();
+        assert_eq!(
+            Did::new(MAINNET_CHAIN_ID, address).to_string(),
+            format!("did:ethr:{CHECKSUMMED}")
+        );
+        assert_eq!(
+            Did::new(SEPOLIA_CHAIN_ID, address).to_string(),
+            format!("did:ethr:sepolia:{CHECKSUMMED}")
+        );
+        assert_eq!(
+            Did::new(5, address).to_string(),
+            format!("did:ethr:0x5:{CHECKSUMMED}")
+        );
+        assert_eq!(
+            Did::parse_on_chain(ADDRESS, SEPOLIA_CHAIN_ID).unwrap(),
+            Did::new(SEPOLIA_CHAIN_ID, address)
+        );
+        assert_eq!(
+            Did::parse_on_chain(&format!("did:ethr:{ADDRESS}"), SEPOLIA_CHAIN_ID).unwrap(),
+            Did::new(MAINNET_CHAIN_ID, address)
+        );
+    }
+
+    #[test]
+    fn test_compressed_public_key() {
+        let bytes = hex::decode(&PUBLIC_KEY[2..]).unwrap();
+        let key = CompressedPublicKey::from_bytes(&bytes).unwrap();
+        assert_eq!(key.as_bytes().as_slice(), bytes.as_slice());
+        assert_eq!(
+            key.address(),
+            "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+                .parse()
+                .unwrap()
+        );
+        assert!(CompressedPublicKey::from_bytes(&bytes[1..]).is_none());
+        let mut off_curve = [0xff; 33];
+        off_curve[0] = 0x02;
+        assert!(CompressedPublicKey::from_bytes(&off_curve).is_none());
+    }
+
+    #[test]
+    fn test_parse_public_key_did() {
+        let did = format!("did:ethr:dev:{PUBLIC_KEY}").parse::<Did>().unwrap();
+        assert!(matches!(did.identifier(), Identifier::PublicKey(_)));
+        assert_eq!(
+            did.address(),
+            "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(did.to_string(), format!("did:ethr:dev:{PUBLIC_KEY}"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            (
+                "ethr:0xb9c5714089478a327f09197987f16f9e5d936e8a".to_string(),
+                DidParseError::InvalidFormat(
+                    "ethr:0xb9c5714089478a327f09197987f16f9e5d936e8a".to_string(),
+                ),
+            ),
+            (
+                format!("did:web:{ADDRESS}"),
+                DidParseError::UnsupportedMethod("web".to_string()),
+            ),
+            (
+                format!("did:ethr:goerli:{ADDRESS}"),
+                DidParseError::UnknownNetwork("goerli".to_string()),
+            ),
+            (
+                format!("did:ethr:0xzz:{ADDRESS}"),
+                DidParseError::InvalidChainId("0xzz".to_string()),
+            ),
+            (
+                "did:ethr:0x1234".to_string(),
+                DidParseError::InvalidIdentifier("0x1234".to_string()),
+            ),
+            (
+                "did:ethr:b9c5714089478a327f09197987f16f9e5d936e8a".to_string(),
+                DidParseError::InvalidIdentifier(
+                    "b9c5714089478a327f09197987f16f9e5d936e8a".to_string(),
+                ),
+            ),
+            (
+                "did:ethr:0xB9c5714089478a327F09197987f16f9E5d936E8a".to_string(),
+                DidParseError::InvalidChecksum(
+                    "0xB9c5714089478a327F09197987f16f9E5d936E8a".to_string(),
+                ),
+            ),
+            (
+                format!("did:ethr:0x05{}", "ff".repeat(32)),
+                DidParseError::InvalidPublicKey(format!("0x05{}", "ff".repeat(32))),
+            ),
+            (
+                format!("did:ethr:dev:sepolia:{ADDRESS}"),
+                DidParseError::InvalidFormat(format!("did:ethr:dev:sepolia:{ADDRESS}")),
+            ),
+        ];
+        for (did, expected) in cases {
+            assert_eq!(did.parse::<Did>().unwrap_err(), expected, "{did}");
+        }
+    }
+
+    #[test]
+    fn test_did_serde() {
+        let did = format!("did:ethr:sepolia:{CHECKSUMMED}")
+            .parse::<Did>()
+            .unwrap();
+        let json = serde_json::to_string(&did).unwrap();
+        assert_eq!(json, format!("\"did:ethr:sepolia:{CHECKSUMMED}\""));
+        assert_eq!(serde_json::from_str::<Did>(&json).unwrap(), did);
+    }
+}
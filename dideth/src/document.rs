@@ -0,0 +1,634 @@
+use ethers::{
+    types::{Address, Bytes, U256},
+    utils::to_checksum,
+};
+
+use crate::{
+    resolver::{bytes32_to_string, is_valid},
+    DIDRegistryEvents, DelegateType, Did, HistoryEntry, Identifier, KeyAlgorithm, KeyEncoding,
+    KeyPurpose, PublicKey, PublicKeyAttribute,
+};
+
+pub const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+pub const SECP256K1_RECOVERY_CONTEXT: &str =
+    "https://w3id.org/security/suites/secp256k1recovery-2020/v2";
+
+/// Verification method types used by did:ethr documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum VerificationMethodType {
+    /// an Ethereum account, verified by recovering the signer address
+    EcdsaSecp256k1RecoveryMethod2020,
+    EcdsaSecp256k1VerificationKey2019,
+    Ed25519VerificationKey2018,
+    X25519KeyAgreementKey2019,
+    RsaVerificationKey2018,
+}
+
+impl VerificationMethodType {
+    /// the JSON-LD context defining this method type
+    pub fn context(&self) -> &'static str {
+        match self {
+            VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020 => SECP256K1_RECOVERY_CONTEXT,
+            VerificationMethodType::EcdsaSecp256k1VerificationKey2019 => {
+                "https://w3id.org/security/suites/secp256k1-2019/v1"
+            }
+            VerificationMethodType::Ed25519VerificationKey2018 => {
+                "https://w3id.org/security/suites/ed25519-2018/v1"
+            }
+            VerificationMethodType::X25519KeyAgreementKey2019 => {
+                "https://w3id.org/security/suites/x25519-2019/v1"
+            }
+            VerificationMethodType::RsaVerificationKey2018 => "https://w3id.org/security/v2",
+        }
+    }
+}
+
+/// A verification method of a did:ethr document
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub method_type: VerificationMethodType,
+    pub controller: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blockchain_account_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_hex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_base58: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_base64: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+}
+
+impl VerificationMethod {
+    /// an Ethereum account on `chain_id` in CAIP-10 form
+    pub fn account(id: String, controller: &str, chain_id: u64, account: Address) -> Self {
+        Self {
+            id,
+            method_type: VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020,
+            controller: controller.to_string(),
+            blockchain_account_id: Some(format!(
+                "eip155:{chain_id}:{}",
+                to_checksum(&account, None)
+            )),
+            public_key_hex: None,
+            public_key_base58: None,
+            public_key_base64: None,
+            public_key_multibase: None,
+        }
+    }
+
+    /// a key published with a `did/pub` attribute
+    pub fn public_key(
+        id: String,
+        controller: &str,
+        attribute: &PublicKeyAttribute,
+        value: &[u8],
+    ) -> Self {
+        let mut method = Self {
+            id,
+            method_type: attribute.algorithm.method_type(),
+            controller: controller.to_string(),
+            blockchain_account_id: None,
+            public_key_hex: None,
+            public_key_base58: None,
+            public_key_base64: None,
+            public_key_multibase: None,
+        };
+        match attribute.public_key(value) {
+            PublicKey::Hex(key) => method.public_key_hex = Some(key),
+            PublicKey::Base58(key) => method.public_key_base58 = Some(key),
+            PublicKey::Base64(key) => method.public_key_base64 = Some(key),
+            PublicKey::Multibase(key) => method.public_key_multibase = Some(key),
+        }
+        method
+    }
+}
+
+/// how the public key of a public key identifier is published
+const CONTROLLER_KEY: PublicKeyAttribute = PublicKeyAttribute {
+    algorithm: KeyAlgorithm::Secp256k1,
+    purpose: KeyPurpose::SigAuth,
+    encoding: KeyEncoding::Hex,
+};
+
+/// Registry entries that publish a verification method, later changes to the same entry replace it
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MethodKey {
+    Delegate(DelegateType, Address),
+    Attribute(String, Bytes),
+}
+
+/// A service published with a `did/svc/<type>` attribute
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub service_type: String,
+    /// a URL, or the JSON object or array the owner published
+    pub service_endpoint: serde_json::Value,
+}
+
+impl Service {
+    pub fn new(id: String, service_type: &str, value: &[u8]) -> Self {
+        let endpoint = String::from_utf8_lossy(value).to_string();
+        let service_endpoint = match serde_json::from_str::<serde_json::Value>(&endpoint) {
+            Ok(json) if json.is_object() || json.is_array() => json,
+            _ => serde_json::Value::String(endpoint),
+        };
+        Self {
+            id,
+            service_type: service_type.to_string(),
+            service_endpoint,
+        }
+    }
+}
+
+/// A W3C DID document
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    pub verification_method: Vec<VerificationMethod>,
+    pub authentication: Vec<String>,
+    pub assertion_method: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_agreement: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub service: Vec<Service>,
+}
+
+impl DidDocument {
+    /// the empty document of a DID whose owner is the zero address
+    pub fn deactivated(id: &str) -> Self {
+        Self {
+            context: vec![DID_CONTEXT.to_string()],
+            id: id.to_string(),
+            verification_method: Vec::new(),
+            authentication: Vec::new(),
+            assertion_method: Vec::new(),
+            key_agreement: Vec::new(),
+            service: Vec::new(),
+        }
+    }
+
+    /// build the document of `did` from its registry history, oldest event first
+    ///
+    /// `id` is `did` as the caller wrote it, the document and its method ids keep
+    /// that spelling.
+    /// The owner is the `#controller` method.  Delegates and `did/pub` keys are
+    /// numbered in the order their events were emitted, revocations included, so
+    /// `#delegate-N` ids are stable as the history grows, as are the `#service-N`
+    /// ids of `did/svc` services.  `sigAuth` methods may also authenticate and
+    /// `enc` keys are only used for key agreement.
+    ///
+    /// A zero address owner means the DID is deactivated and the document is empty.
+    /// A public key identifier adds a `#controllerKey` method while the identity
+    /// still owns itself, it is dropped once ownership is transferred.
+    pub fn from_history(
+        id: &str,
+        did: &Did,
+        owner: Address,
+        history: &[HistoryEntry],
+        now: U256,
+    ) -> Self {
+        if owner.is_zero() {
+            return Self::deactivated(id);
+        }
+        let chain_id = did.chain_id();
+        let controller_key = match did.identifier() {
+            Identifier::PublicKey(key) if did.address() == owner => Some(*key),
+            _ => None,
+        };
+        let did = id;
+        let controller_id = format!("{did}#controller");
+        let mut verification_method = vec![VerificationMethod::account(
+            controller_id.clone(),
+            did,
+            chain_id,
+            owner,
+        )];
+        let mut authentication = vec![controller_id.clone()];
+        let mut assertion_method = vec![controller_id];
+        if let Some(key) = controller_key {
+            let controller_key_id = format!("{did}#controllerKey");
+            verification_method.push(VerificationMethod::public_key(
+                controller_key_id.clone(),
+                did,
+                &CONTROLLER_KEY,
+                key.as_bytes(),
+            ));
+            authentication.push(controller_key_id.clone());
+            assertion_method.push(controller_key_id);
+        }
+        let mut key_agreement = Vec::new();
+
+        let mut service_count = 0;
+        let mut services: Vec<(MethodKey, Service)> = Vec::new();
+        let mut delegate_count = 0;
+        let mut methods: Vec<(MethodKey, VerificationMethod, KeyPurpose)> = Vec::new();
+        for entry in history.iter() {
+            let (key, valid_to, method) = match &entry.event {
+                DIDRegistryEvents::DiddelegateChangedFilter(change) => {
+                    delegate_count += 1;
+                    let Ok(delegate_type) = DelegateType::from_bytes32(&change.delegate_type)
+                    else {
+                        tracing::warn!("unknown delegate type for {:#x}", change.delegate);
+                        continue;
+                    };
+                    let method = VerificationMethod::account(
+                        format!("{did}#delegate-{delegate_count}"),
+                        did,
+                        chain_id,
+                        change.delegate,
+                    );
+                    let purpose = match delegate_type {
+                        DelegateType::VeriKey => KeyPurpose::VeriKey,
+                        DelegateType::SigAuth => KeyPurpose::SigAuth,
+                    };
+                    (
+                        MethodKey::Delegate(delegate_type, change.delegate),
+                        change.valid_to,
+                        (method, purpose),
+                    )
+                }
+                DIDRegistryEvents::DidattributeChangedFilter(change) => {
+                    let name = bytes32_to_string(&change.name);
+                    if let Some(service_type) = name.strip_prefix("did/svc/") {
+                        service_count += 1;
+                        let key = MethodKey::Attribute(name.clone(), change.value.clone());
+                        if is_valid(change.valid_to, now) {
+                            let service = Service::new(
+                                format!("{did}#service-{service_count}"),
+                                service_type,
+                                &change.value,
+                            );
+                            match services.iter_mut().find(|(k, _)| *k == key) {
+                                Some(existing) => *existing = (key, service),
+                                None => services.push((key, service)),
+                            }
+                        } else {
+                            services.retain(|(k, _)| *k != key);
+                        }
+                        continue;
+                    }
+                    if !name.starts_with("did/pub/") {
+                        continue;
+                    }
+                    delegate_count += 1;
+                    let attribute = match name.parse::<PublicKeyAttribute>() {
+                        Ok(attribute) => attribute,
+                        Err(err) => {
+                            tracing::warn!("skipping public key: {err}");
+                            continue;
+                        }
+                    };
+                    let method = VerificationMethod::public_key(
+                        format!("{did}#delegate-{delegate_count}"),
+                        did,
+                        &attribute,
+                        &change.value,
+                    );
+                    (
+                        MethodKey::Attribute(name, change.value.clone()),
+                        change.valid_to,
+                        (method, attribute.purpose),
+                    )
+                }
+                DIDRegistryEvents::DidownerChangedFilter(_) => continue,
+            };
+            if is_valid(valid_to, now) {
+                let (method, purpose) = method;
+                match methods.iter_mut().find(|(k, _, _)| *k == key) {
+                    Some(existing) => *existing = (key, method, purpose),
+                    None => methods.push((key, method, purpose)),
+                }
+            } else {
+                methods.retain(|(k, _, _)| *k != key);
+            }
+        }
+
+        for (_, method, purpose) in methods {
+            match purpose {
+                KeyPurpose::VeriKey => assertion_method.push(method.id.clone()),
+                KeyPurpose::SigAuth => {
+                    authentication.push(method.id.clone());
+                    assertion_method.push(method.id.clone());
+                }
+                KeyPurpose::Enc => key_agreement.push(method.id.clone()),
+            }
+            verification_method.push(method);
+        }
+
+        let mut context = vec![DID_CONTEXT.to_string()];
+        for method in verification_method.iter() {
+            let method_context = method.method_type.context().to_string();
+            if !context.contains(&method_context) {
+                context.push(method_context);
+            }
+        }
+
+        Self {
+            context,
+            id: did.to_string(),
+            verification_method,
+            authentication,
+            assertion_method,
+            key_agreement,
+            service: services.into_iter().map(|(_, service)| service).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DidattributeChangedFilter, DiddelegateChangedFilter};
+    use ethers::utils::format_bytes32_string;
+
+    const DID: &str = "did:ethr:0x1111111111111111111111111111111111111111";
+    const NOW: u64 = 1_000;
+
+    fn did() -> Did {
+        DID.parse().unwrap()
+    }
+
+    fn delegate(delegate_type: DelegateType, delegate: Address, valid_to: u64) -> HistoryEntry {
+        HistoryEntry {
+            block_number: 0,
+            log_index: 0,
+            event: DiddelegateChangedFilter {
+                identity: Address::repeat_byte(0x11),
+                delegate_type: delegate_type.to_bytes32(),
+                delegate,
+                valid_to: valid_to.into(),
+                previous_change: U256::zero(),
+            }
+            .into(),
+        }
+    }
+
+    #[test]
+    fn test_controller_only() {
+        let owner = Address::repeat_byte(0x11);
+        let document = DidDocument::from_history(DID, &did(), owner, &[], NOW.into());
+        assert_eq!(document.verification_method.len(), 1);
+        let controller = &document.verification_method[0];
+        assert_eq!(controller.id, format!("{DID}#controller"));
+        assert_eq!(
+            controller.blockchain_account_id.as_deref(),
+            Some("eip155:1:0x1111111111111111111111111111111111111111")
+        );
+        assert_eq!(document.authentication, vec![format!("{DID}#controller")]);
+        assert_eq!(document.assertion_method, vec![format!("{DID}#controller")]);
+    }
+
+    #[test]
+    fn test_delegates() {
+        let owner = Address::repeat_byte(0x11);
+        let veri_key = Address::repeat_byte(0x22);
+        let sig_auth = Address::repeat_byte(0x33);
+        let history = vec![
+            delegate(DelegateType::VeriKey, veri_key, 2_000),
+            delegate(DelegateType::SigAuth, sig_auth, 2_000),
+            delegate(DelegateType::VeriKey, veri_key, 900),
+            delegate(DelegateType::VeriKey, veri_key, 2_000),
+        ];
+        let document = DidDocument::from_history(DID, &did(), owner, &history, NOW.into());
+        let ids = document
+            .verification_method
+            .iter()
+            .map(|method| method.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                format!("{DID}#controller"),
+                format!("{DID}#delegate-2"),
+                format!("{DID}#delegate-4"),
+            ]
+        );
+        assert_eq!(
+            document.authentication,
+            vec![format!("{DID}#controller"), format!("{DID}#delegate-2")]
+        );
+        assert_eq!(document.assertion_method, ids);
+    }
+
+    fn public_key(name: &str, value: &[u8], valid_to: u64) -> HistoryEntry {
+        HistoryEntry {
+            block_number: 0,
+            log_index: 0,
+            event: DidattributeChangedFilter {
+                identity: Address::repeat_byte(0x11),
+                name: format_bytes32_string(name).unwrap(),
+                value: Bytes::from(value.to_vec()),
+                valid_to: valid_to.into(),
+                previous_change: U256::zero(),
+            }
+            .into(),
+        }
+    }
+
+    #[test]
+    fn test_public_keys() {
+        let owner = Address::repeat_byte(0x11);
+        let history = vec![
+            public_key("did/pub/Secp256k1/veriKey/hex", &[0x02; 33], 2_000),
+            delegate(DelegateType::VeriKey, Address::repeat_byte(0x22), 900),
+            public_key("did/pub/Ed25519/sigAuth/base58", &[0x01; 32], 2_000),
+            public_key("did/pub/X25519/enc/base64", &[0xff; 32], 2_000),
+            public_key("did/pub/P256/veriKey/hex", &[0x03; 33], 2_000),
+            public_key("did/svc/MessagingService", b"https://example.com", 2_000),
+        ];
+        let document = DidDocument::from_history(DID, &did(), owner, &history, NOW.into());
+        let methods = &document.verification_method;
+        assert_eq!(methods.len(), 4);
+
+        assert_eq!(methods[1].id, format!("{DID}#delegate-1"));
+        assert_eq!(
+            methods[1].method_type,
+            VerificationMethodType::EcdsaSecp256k1VerificationKey2019
+        );
+        assert_eq!(methods[1].public_key_hex, Some("02".repeat(33)));
+
+        assert_eq!(methods[2].id, format!("{DID}#delegate-3"));
+        assert_eq!(
+            methods[2].method_type,
+            VerificationMethodType::Ed25519VerificationKey2018
+        );
+        assert_eq!(
+            methods[2].public_key_base58.as_deref(),
+            Some(bs58::encode([0x01; 32]).into_string().as_str())
+        );
+
+        assert_eq!(methods[3].id, format!("{DID}#delegate-4"));
+        assert_eq!(
+            methods[3].method_type,
+            VerificationMethodType::X25519KeyAgreementKey2019
+        );
+        assert!(methods[3].public_key_base64.is_some());
+
+        assert_eq!(
+            document.authentication,
+            vec![format!("{DID}#controller"), format!("{DID}#delegate-3")]
+        );
+        assert_eq!(
+            document.assertion_method,
+            vec![
+                format!("{DID}#controller"),
+                format!("{DID}#delegate-1"),
+                format!("{DID}#delegate-3"),
+            ]
+        );
+        assert_eq!(document.key_agreement, vec![format!("{DID}#delegate-4")]);
+        assert_eq!(
+            document.context,
+            vec![
+                DID_CONTEXT,
+                SECP256K1_RECOVERY_CONTEXT,
+                "https://w3id.org/security/suites/secp256k1-2019/v1",
+                "https://w3id.org/security/suites/ed25519-2018/v1",
+                "https://w3id.org/security/suites/x25519-2019/v1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_revoked_public_key() {
+        let owner = Address::repeat_byte(0x11);
+        let name = "did/pub/X25519/enc/multibase";
+        let history = vec![
+            public_key(name, &[0x01; 32], 2_000),
+            public_key(name, &[0x01; 32], 0),
+        ];
+        let document = DidDocument::from_history(DID, &did(), owner, &history, NOW.into());
+        assert_eq!(document.verification_method.len(), 1);
+        assert!(document.key_agreement.is_empty());
+
+        let document = DidDocument::from_history(DID, &did(), owner, &history[..1], NOW.into());
+        assert_eq!(
+            document.verification_method[1].public_key_multibase,
+            Some(format!("z{}", bs58::encode([0x01; 32]).into_string()))
+        );
+        assert_eq!(document.key_agreement, vec![format!("{DID}#delegate-1")]);
+    }
+
+    #[test]
+    fn test_services() {
+        let owner = Address::repeat_byte(0x11);
+        let history = vec![
+            public_key("did/svc/MessagingService", b"https://a.example", 2_000),
+            public_key("did/svc/MessagingService", b"https://b.example", 2_000),
+            public_key("did/svc/MessagingService", b"https://a.example", 0),
+            public_key(
+                "did/svc/LinkedDomains",
+                br#"{"origins":["https://example.com"]}"#,
+                2_000,
+            ),
+            public_key("did/svc/Mirrors", br#"["https://c.example"]"#, 2_000),
+            public_key("did/svc/Expired", b"https://d.example", 900),
+        ];
+        let document = DidDocument::from_history(DID, &did(), owner, &history, NOW.into());
+        let json = serde_json::to_value(&document.service).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "id": format!("{DID}#service-2"),
+                    "type": "MessagingService",
+                    "serviceEndpoint": "https://b.example",
+                },
+                {
+                    "id": format!("{DID}#service-4"),
+                    "type": "LinkedDomains",
+                    "serviceEndpoint": { "origins": ["https://example.com"] },
+                },
+                {
+                    "id": format!("{DID}#service-5"),
+                    "type": "Mirrors",
+                    "serviceEndpoint": ["https://c.example"],
+                },
+            ])
+        );
+        // services do not consume delegate ids
+        assert_eq!(document.verification_method.len(), 1);
+    }
+
+    #[test]
+    fn test_controller_key() {
+        // the compressed public key of the secret key 1
+        let key = "0x0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let id = format!("did:ethr:{key}");
+        let did = id.parse::<Did>().unwrap();
+        let document = DidDocument::from_history(&id, &did, did.address(), &[], NOW.into());
+        let methods = &document.verification_method;
+        assert_eq!(methods.len(), 2);
+        assert_eq!(
+            methods[0].blockchain_account_id.as_deref(),
+            Some("eip155:1:0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf")
+        );
+        assert_eq!(methods[1].id, format!("{id}#controllerKey"));
+        assert_eq!(
+            methods[1].method_type,
+            VerificationMethodType::EcdsaSecp256k1VerificationKey2019
+        );
+        assert_eq!(methods[1].public_key_hex.as_deref(), Some(&key[2..]));
+        assert_eq!(
+            document.authentication,
+            vec![format!("{id}#controller"), format!("{id}#controllerKey")]
+        );
+        assert_eq!(document.assertion_method, document.authentication);
+
+        // the key no longer controls the identity once ownership is transferred
+        let owner = Address::repeat_byte(0x22);
+        let document = DidDocument::from_history(&id, &did, owner, &[], NOW.into());
+        assert_eq!(document.verification_method.len(), 1);
+        assert_eq!(document.authentication, vec![format!("{id}#controller")]);
+    }
+
+    #[test]
+    fn test_deactivated() {
+        let history = vec![
+            delegate(DelegateType::SigAuth, Address::repeat_byte(0x22), 2_000),
+            public_key("did/svc/MessagingService", b"https://a.example", 2_000),
+        ];
+        let document =
+            DidDocument::from_history(DID, &did(), Address::zero(), &history, NOW.into());
+        assert_eq!(document, DidDocument::deactivated(DID));
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "@context": [DID_CONTEXT],
+                "id": DID,
+                "verificationMethod": [],
+                "authentication": [],
+                "assertionMethod": [],
+            })
+        );
+    }
+
+    #[test]
+    fn test_document_json() {
+        let owner = Address::repeat_byte(0x11);
+        let document = DidDocument::from_history(DID, &did(), owner, &[], NOW.into());
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["@context"][0], DID_CONTEXT);
+        assert_eq!(json["id"], DID);
+        assert_eq!(
+            json["verificationMethod"][0]["type"],
+            "EcdsaSecp256k1RecoveryMethod2020"
+        );
+        assert_eq!(json["verificationMethod"][0]["controller"], DID);
+        assert!(json["verificationMethod"][0]["blockchainAccountId"].is_string());
+        let parsed: DidDocument = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, document);
+    }
+}
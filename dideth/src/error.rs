@@ -0,0 +1,129 @@
+use ethers::{
+    contract::ContractError,
+    providers::Middleware,
+    signers::WalletError,
+    types::{Address, H256, U256},
+    utils::format_bytes32_string,
+};
+use std::str::FromStr;
+
+use crate::DidParseError;
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors returned by `dideth`
+#[derive(Debug, thiserror::Error)]
+pub enum DidEthError {
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("invalid did: {0}")]
+    InvalidDid(#[from] DidParseError),
+    #[error("attribute name is longer than 32 bytes: {0}")]
+    AttributeNameTooLong(String),
+    #[error("invalid attribute name: {0}")]
+    InvalidAttributeName(String),
+    #[error("unknown delegate type: {0}")]
+    UnknownDelegateType(String),
+    #[error("unknown network: {0}")]
+    UnknownNetwork(String),
+    #[error("invalid versionTime, expected an ISO 8601 timestamp: {0}")]
+    InvalidVersionTime(String),
+    #[error("invalid wallet key: {0}")]
+    InvalidWallet(#[from] WalletError),
+    #[error("invalid meta transaction: {0}")]
+    InvalidMetaTransaction(String),
+    #[error("rpc transport failure: {0}")]
+    Transport(#[source] BoxedError),
+    #[error("the registry config has no chain id to place {0} on")]
+    MissingChainId(String),
+    #[error("connected to chain {connected}, expected chain {expected}")]
+    ChainMismatch { connected: U256, expected: u64 },
+    #[error("contract reverted: {reason}")]
+    ContractRevert { reason: String },
+    #[error("{signer:#x} is not the owner of {identity:#x}, owner is {owner:#x}")]
+    NotOwner {
+        identity: Address,
+        owner: Address,
+        signer: Address,
+    },
+    #[error("{0:#x} is deactivated")]
+    Deactivated(Address),
+    #[error("refusing to transfer {0:#x} to the zero address, use deactivate instead")]
+    ZeroAddressOwner(Address),
+    #[error("owner of {identity:#x} is {owner:#x} after transfer, expected {expected:#x}")]
+    OwnerNotChanged {
+        identity: Address,
+        owner: Address,
+        expected: Address,
+    },
+    #[error("transaction {0:#x} was not confirmed in time")]
+    ConfirmationTimeout(H256),
+    #[error("transaction {0:#x} was dropped")]
+    TransactionDropped(H256),
+    #[error("transaction {0:#x} failed")]
+    TransactionFailed(H256),
+    #[error("code at {0:#x} is not the bundled DIDRegistry")]
+    RegistryCodeMismatch(Address),
+    #[error("no registry events in block {0} of the change history")]
+    MissingHistory(u64),
+    #[error("block {0} not found")]
+    BlockNotFound(u64),
+    #[error("malformed log data: {0}")]
+    MalformedLog(String),
+}
+
+impl DidEthError {
+    pub(crate) fn transport<E: std::error::Error + Send + Sync + 'static>(err: E) -> Self {
+        DidEthError::Transport(Box::new(err))
+    }
+}
+
+impl<M: Middleware + 'static> From<ContractError<M>> for DidEthError {
+    fn from(err: ContractError<M>) -> Self {
+        if let Some(reason) = err.decode_revert::<String>() {
+            DidEthError::ContractRevert { reason }
+        } else if let Some(data) = err.as_revert() {
+            DidEthError::ContractRevert {
+                reason: data.to_string(),
+            }
+        } else {
+            DidEthError::transport(err)
+        }
+    }
+}
+
+pub(crate) fn parse_address(address: &str) -> Result<Address, DidEthError> {
+    Address::from_str(address).map_err(|_| DidEthError::InvalidAddress(address.to_string()))
+}
+
+pub(crate) fn name_to_bytes32(name: &str) -> Result<[u8; 32], DidEthError> {
+    format_bytes32_string(name).map_err(|_| DidEthError::AttributeNameTooLong(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_address() {
+        assert!(parse_address("0x6CEb0bF1f28ca4165d5C0A04f61DC733987eD6ad").is_ok());
+        assert!(matches!(
+            parse_address("my_public_key"),
+            Err(DidEthError::InvalidAddress(address)) if address == "my_public_key"
+        ));
+    }
+
+    #[test]
+    fn test_name_to_bytes32() {
+        assert!(name_to_bytes32("did/svc/MessagingService").is_ok());
+        assert!(name_to_bytes32(&"a".repeat(32)).is_ok());
+        assert!(matches!(
+            name_to_bytes32("did/pub/Secp256k1/veriKey/base58btc"),
+            Err(DidEthError::AttributeNameTooLong(_))
+        ));
+        assert_eq!(
+            name_to_bytes32(&"a".repeat(33)).unwrap_err().to_string(),
+            format!("attribute name is longer than 32 bytes: {}", "a".repeat(33))
+        );
+    }
+}
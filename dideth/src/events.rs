@@ -0,0 +1,107 @@
+use ethers::types::{Address, Bytes, U256};
+
+use crate::{
+    resolver::bytes32_to_string, DIDRegistryEvents, DidattributeChangedFilter,
+    DiddelegateChangedFilter, DidownerChangedFilter,
+};
+
+/// Any decoded registry event
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RegistryEvent {
+    AttributeChanged(AttributeChanged),
+    DelegateChanged(DelegateChanged),
+    OwnerChanged(OwnerChanged),
+}
+
+impl RegistryEvent {
+    /// the identity the event was emitted for
+    pub fn identity(&self) -> Address {
+        match self {
+            RegistryEvent::AttributeChanged(event) => event.identity,
+            RegistryEvent::DelegateChanged(event) => event.identity,
+            RegistryEvent::OwnerChanged(event) => event.identity,
+        }
+    }
+}
+
+impl From<DIDRegistryEvents> for RegistryEvent {
+    fn from(event: DIDRegistryEvents) -> Self {
+        match event {
+            DIDRegistryEvents::DidattributeChangedFilter(event) => {
+                RegistryEvent::AttributeChanged(event.into())
+            }
+            DIDRegistryEvents::DiddelegateChangedFilter(event) => {
+                RegistryEvent::DelegateChanged(event.into())
+            }
+            DIDRegistryEvents::DidownerChangedFilter(event) => {
+                RegistryEvent::OwnerChanged(event.into())
+            }
+        }
+    }
+}
+
+/// A decoded `DIDAttributeChanged` event
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributeChanged {
+    pub identity: Address,
+    pub name: String,
+    pub value: Bytes,
+    pub valid_to: U256,
+    pub previous_change: U256,
+}
+
+impl From<DidattributeChangedFilter> for AttributeChanged {
+    fn from(event: DidattributeChangedFilter) -> Self {
+        Self {
+            identity: event.identity,
+            name: bytes32_to_string(&event.name),
+            value: event.value,
+            valid_to: event.valid_to,
+            previous_change: event.previous_change,
+        }
+    }
+}
+
+/// A decoded `DIDDelegateChanged` event
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegateChanged {
+    pub identity: Address,
+    pub delegate_type: String,
+    pub delegate: Address,
+    pub valid_to: U256,
+    pub previous_change: U256,
+}
+
+impl From<DiddelegateChangedFilter> for DelegateChanged {
+    fn from(event: DiddelegateChangedFilter) -> Self {
+        Self {
+            identity: event.identity,
+            delegate_type: bytes32_to_string(&event.delegate_type),
+            delegate: event.delegate,
+            valid_to: event.valid_to,
+            previous_change: event.previous_change,
+        }
+    }
+}
+
+/// A decoded `DIDOwnerChanged` event
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnerChanged {
+    pub identity: Address,
+    pub owner: Address,
+    pub previous_change: U256,
+}
+
+impl From<DidownerChangedFilter> for OwnerChanged {
+    fn from(event: DidownerChangedFilter) -> Self {
+        Self {
+            identity: event.identity,
+            owner: event.owner,
+            previous_change: event.previous_change,
+        }
+    }
+}
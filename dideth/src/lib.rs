@@ -1,17 +1,63 @@
-use anyhow::Error;
-use ethabi::Token;
 use ethers::{
-    contract::abigen,
+    contract::{abigen, ContractCall},
     core::k256::ecdsa::SigningKey,
     prelude::{LocalWallet, Provider, SignerMiddleware, Wallet},
     providers::{Middleware, Ws},
-    types::{Address, Filter, H160, H256, U256, U64},
-    utils::format_bytes32_string,
+    signers::Signer,
+    types::{Address, U256, U64},
 };
 
-use std::{collections::HashSet, str::FromStr, sync::Arc};
+use std::{future::Future, sync::Arc, time::Duration};
 
-use sha3::{Digest, Sha3_256};
+mod attribute;
+mod backend;
+mod cache;
+mod cassette;
+mod config;
+mod delegate;
+mod deploy;
+mod did;
+mod document;
+mod error;
+mod events;
+#[cfg(any(test, feature = "testing"))]
+mod memory;
+mod options;
+mod receipt;
+mod resolution;
+mod resolver;
+mod signed;
+
+use error::{name_to_bytes32, parse_address};
+
+pub use attribute::{KeyAlgorithm, KeyEncoding, KeyPurpose, PublicKey, PublicKeyAttribute};
+pub use backend::RegistryBackend;
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::DiskCache;
+pub use cache::{CachedResolution, CachingResolver, MemoryCache, ResolutionCache};
+pub use cassette::{
+    Cassette, CassetteError, Interaction, RecordedError, RecordingTransport, ReplayTransport,
+    Response,
+};
+pub use config::{Network, RegistryConfig, DEV_CHAIN_ID, MAINNET_CHAIN_ID, SEPOLIA_CHAIN_ID};
+pub use delegate::DelegateType;
+pub use deploy::{deploy_registry, verify_registry};
+pub use did::{CompressedPublicKey, Did, DidNetwork, DidParseError, Identifier};
+pub use document::{
+    DidDocument, Service, VerificationMethod, VerificationMethodType, DID_CONTEXT,
+    SECP256K1_RECOVERY_CONTEXT,
+};
+pub use error::DidEthError;
+pub use events::{AttributeChanged, DelegateChanged, OwnerChanged, RegistryEvent};
+#[cfg(any(test, feature = "testing"))]
+pub use memory::InMemoryRegistry;
+pub use options::ResolutionOptions;
+pub use receipt::RegistryReceipt;
+pub use resolution::{
+    DidDocumentMetadata, DidResolutionMetadata, ResolutionError, ResolutionResult, DID_LD_JSON,
+};
+pub use resolver::{active_attributes, active_delegates, owner_at, HistoryEntry, Resolver};
+pub use signed::{sign_bundle, Operation, SignedMetaTransaction};
 
 type WalletType = Wallet<SigningKey>;
 type Client = SignerMiddleware<Provider<Ws>, WalletType>;
@@ -19,6 +65,8 @@ type Client = SignerMiddleware<Provider<Ws>, WalletType>;
 pub const REQUIRED_CONFIRMATIONS: usize = 2;
 pub const DID_ETH_REGISTRY: &str = "0xd1D374DDE031075157fDb64536eF5cC13Ae75000";
 pub const DATA_LIFETIME: u64 = 86400 * 365; // 1 year
+pub const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(600);
+pub const LOG_RANGE: u64 = 10_000;
 
 // Generate rust bindings for the DIDRegistry contract
 abigen!(
@@ -27,186 +75,538 @@ abigen!(
     derives(serde::Deserialize, serde::Serialize)
 );
 
-pub struct DidEthRegistry {
-    contract: DIDRegistry<Client>,
-    signer: Arc<Client>,
+/// Client for the ERC-1056 registry over any ethers middleware
+///
+/// Reads work with any provider, the write operations are only available when
+/// the middleware implements `Signing`, such as `SignerMiddleware`.
+pub struct DidEthRegistry<M = Client> {
+    contract: DIDRegistry<M>,
+    client: Arc<M>,
+    resolver: Resolver<M>,
+}
+
+/// A middleware that signs the transactions it sends, required by the registry writes
+///
+/// A registry over a plain provider has no write operations:
+/// ```compile_fail
+/// # async fn write(registry: dideth::DidEthRegistry<ethers::providers::Provider<ethers::providers::Http>>) {
+/// registry.set_attribute("name".to_string(), "value".to_string()).await;
+/// # }
+/// ```
+pub trait Signing: Middleware {
+    /// the account transactions are signed by
+    fn signer_address(&self) -> Address;
+}
+
+impl<M: Middleware, S: Signer> Signing for SignerMiddleware<M, S> {
+    fn signer_address(&self) -> Address {
+        self.address()
+    }
+}
+
+/// `future`'s output, or `None` once `timeout` has passed
+#[cfg(not(target_arch = "wasm32"))]
+async fn within<F: Future>(timeout: Duration, future: F) -> Option<F::Output> {
+    tokio::time::timeout(timeout, future).await.ok()
+}
+
+/// there is no tokio timer in the browser, wait without a timeout
+#[cfg(target_arch = "wasm32")]
+async fn within<F: Future>(_timeout: Duration, future: F) -> Option<F::Output> {
+    Some(future.await)
 }
 
-fn wallet_from_key(wallet_key: &str) -> Result<WalletType, Error> {
+fn wallet_from_key(wallet_key: &str) -> Result<WalletType, DidEthError> {
     let wallet = wallet_key.parse::<LocalWallet>()?;
     Ok(wallet)
 }
 
-impl DidEthRegistry {
-    pub async fn new(rpc_url: String, wallet_signer: String) -> Result<DidEthRegistry, Error> {
-        // this could be better, but shows how we may accept environment variables from the outside
-        let registry_address = DID_ETH_REGISTRY;
-
-        let provider = Provider::<Ws>::connect(rpc_url).await?;
-        let chain_id = provider.get_chainid().await?;
-        tracing::info!("Connected to chain: {chain_id}");
-
-        // wallet/signer info
-        let wallet_result = wallet_from_key(&wallet_signer);
-        if let Ok(wallet) = wallet_result {
-            tracing::info!("Wallet: {:?}", wallet);
-            let middleware = SignerMiddleware::new_with_provider_chain(provider, wallet)
-                .await
-                .unwrap();
-            let signer = Arc::new(middleware);
-            tracing::info!("Registry Contract Connected: {registry_address}");
-            let registry_address = H160::from_str(registry_address).unwrap();
-            let contract = DIDRegistry::new(registry_address, signer.clone());
+async fn signer_client(rpc_url: String, wallet_signer: String) -> Result<Arc<Client>, DidEthError> {
+    let provider = Provider::<Ws>::connect(rpc_url)
+        .await
+        .map_err(DidEthError::transport)?;
 
-            Ok(Self { contract, signer })
-        } else {
-            let err = wallet_result.unwrap_err();
-            tracing::error!("Wallet error: {:?}", err);
-            Err(err)
-        }
+    // wallet/signer info
+    let wallet_result = wallet_from_key(&wallet_signer);
+    if let Ok(wallet) = wallet_result {
+        tracing::info!("Wallet: {:?}", wallet);
+        let middleware = SignerMiddleware::new_with_provider_chain(provider, wallet)
+            .await
+            .map_err(DidEthError::transport)?;
+        Ok(Arc::new(middleware))
+    } else {
+        let err = wallet_result.unwrap_err();
+        tracing::error!("Wallet error: {:?}", err);
+        Err(err)
     }
+}
 
-    pub async fn owner(&self, id: String) -> Result<String, Error> {
-        let id_as_address = H160::from_str(&id).unwrap();
-        let owner = self.contract.identity_owner(id_as_address).call().await?;
-        let owner_as_string = format!("{owner:#032x}");
-        tracing::info!("Owner: {owner_as_string}");
-        Ok(owner_as_string)
+impl DidEthRegistry<Client> {
+    pub async fn new(
+        rpc_url: String,
+        wallet_signer: String,
+    ) -> Result<DidEthRegistry, DidEthError> {
+        Self::connect(rpc_url, wallet_signer, RegistryConfig::default()).await
+    }
+
+    pub async fn connect(
+        rpc_url: String,
+        wallet_signer: String,
+        config: RegistryConfig,
+    ) -> Result<DidEthRegistry, DidEthError> {
+        let client = signer_client(rpc_url, wallet_signer).await?;
+        Self::from_client(client, config).await
+    }
+
+    /// deploy the bundled registry with the wallet and connect to it
+    pub async fn deploy(
+        rpc_url: String,
+        wallet_signer: String,
+        config: RegistryConfig,
+    ) -> Result<DidEthRegistry, DidEthError> {
+        let client = signer_client(rpc_url, wallet_signer).await?;
+        Self::deploy_from_client(client, config).await
+    }
+
+    pub fn wallet_address(&self) -> String {
+        self.client.address().to_string()
+    }
+}
+
+// read operations
+impl<M: Middleware + 'static> DidEthRegistry<M> {
+    pub async fn from_client(client: Arc<M>, config: RegistryConfig) -> Result<Self, DidEthError> {
+        let resolver = Resolver::from_client(client.clone(), config).await?;
+        let contract = DIDRegistry::new(resolver.registry_address(), client.clone());
+        Ok(Self {
+            contract,
+            client,
+            resolver,
+        })
+    }
+
+    pub fn resolver(&self) -> &Resolver<M> {
+        &self.resolver
+    }
+
+    pub fn registry_address(&self) -> Address {
+        self.contract.address()
+    }
+
+    pub fn config(&self) -> &RegistryConfig {
+        self.resolver.config()
+    }
+
+    /// parse a DID, bare addresses and public keys are placed on the connected chain
+    pub fn did(&self, input: &str) -> Result<Did, DidEthError> {
+        self.resolver.did(input)
+    }
+
+    pub async fn owner(&self, did: &Did) -> Result<String, DidEthError> {
+        self.resolver.owner(did).await
+    }
+
+    pub async fn valid_delegate(
+        &self,
+        did: &Did,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<bool, DidEthError> {
+        self.resolver
+            .valid_delegate(did, delegate_type, delegate)
+            .await
+    }
+
+    /// the registry nonce for meta transactions on `did`, which is tracked per owner
+    pub async fn nonce(&self, did: &Did) -> Result<U256, DidEthError> {
+        self.resolver.nonce(did).await
+    }
+
+    pub async fn attribute(&self, did: &Did) -> Result<Vec<(String, String)>, DidEthError> {
+        self.resolver.attribute(did).await
+    }
+}
+
+// write operations, these require a signing middleware
+impl<M: Signing + 'static> DidEthRegistry<M> {
+    /// deploy the bundled registry with the client's signer and connect to it
+    pub async fn deploy_from_client(
+        client: Arc<M>,
+        config: RegistryConfig,
+    ) -> Result<Self, DidEthError> {
+        let config = deploy_registry(client.clone(), config).await?;
+        Self::from_client(client, config).await
+    }
+
+    /// the account transactions are sent from
+    pub fn sender(&self) -> Address {
+        self.client.signer_address()
+    }
+
+    /// the sender, which is also the identity the direct write operations change
+    async fn active_sender(&self) -> Result<Address, DidEthError> {
+        let sender = self.sender();
+        self.ensure_active(sender).await?;
+        Ok(sender)
+    }
+
+    /// the registry still accepts changes from a deactivated identity, refuse them here
+    async fn ensure_active(&self, identity: Address) -> Result<(), DidEthError> {
+        let did = Did::new(self.resolver.chain_id(), identity);
+        if self.resolver.is_deactivated(&did).await? {
+            return Err(DidEthError::Deactivated(identity));
+        }
+        Ok(())
     }
 
-    pub async fn set_attribute(&self, name: String, value: String) -> Result<String, Error> {
-        let name_b32 = format_bytes32_string(&name).unwrap();
+    pub async fn set_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let name_b32 = name_to_bytes32(&name)?;
+        let identity = self.active_sender().await?;
         let tx = self.contract.set_attribute(
-            self.signer.address(),
+            identity,
             name_b32,
             value.as_bytes().to_vec().into(),
-            U256::from(DATA_LIFETIME),
+            U256::from(self.config().attribute_lifetime),
         );
-        let receipt = tx
-            .send()
-            .await?
-            .confirmations(REQUIRED_CONFIRMATIONS)
-            .await?;
-        Ok(format!("{receipt:?}"))
-    }
-
-    pub async fn revoke_attribute(&self, name: String, value: String) -> Result<String, Error> {
-        let name_b32 = format_bytes32_string(&name).unwrap();
-        let tx = self.contract.revoke_attribute(
-            self.signer.address(),
-            name_b32,
-            value.as_bytes().to_vec().into(),
+        self.send_and_confirm(tx).await
+    }
+
+    pub async fn revoke_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let name_b32 = name_to_bytes32(&name)?;
+        let identity = self.active_sender().await?;
+        let tx =
+            self.contract
+                .revoke_attribute(identity, name_b32, value.as_bytes().to_vec().into());
+        self.send_and_confirm(tx).await
+    }
+
+    pub async fn add_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+        validity: u64,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let delegate_as_address = parse_address(&delegate)?;
+        let identity = self.active_sender().await?;
+        let tx = self.contract.add_delegate(
+            identity,
+            delegate_type.to_bytes32(),
+            delegate_as_address,
+            U256::from(validity),
+        );
+        self.send_and_confirm(tx).await
+    }
+
+    pub async fn revoke_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let delegate_as_address = parse_address(&delegate)?;
+        let identity = self.active_sender().await?;
+        let tx = self.contract.revoke_delegate(
+            identity,
+            delegate_type.to_bytes32(),
+            delegate_as_address,
         );
-        let receipt = tx
-            .send()
-            .await?
-            .confirmations(REQUIRED_CONFIRMATIONS)
-            .await?;
-        Ok(format!("{receipt:?}"))
-    }
-
-    pub async fn attribute(&self, id: String) -> Result<Vec<(String, String)>, Error> {
-        // TODO handle revocation
-        let id_as_address = H160::from_str(&id).unwrap();
-        let mut result_vec = Vec::new();
-        let mut prev_change_result: Result<U256, _> =
-            self.contract.changed(id_as_address).call().await;
-        let current_block = self.signer.get_block_number().await?;
-        let block = self.signer.get_block(current_block).await.unwrap();
-        let mut block_timestamp = U256::zero();
-        if let Some(block) = block {
-            block_timestamp = block.timestamp;
-            tracing::info!("block_timestamp: {block_timestamp}");
+        self.send_and_confirm(tx).await
+    }
+
+    /// transfer control of the identity to a new owner, the signer must be the current owner
+    pub async fn change_owner(
+        &self,
+        did: &Did,
+        new_owner: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let new_owner_as_address = parse_address(&new_owner)?;
+        if new_owner_as_address.is_zero() {
+            return Err(DidEthError::ZeroAddressOwner(did.address()));
+        }
+        self.transfer_owner(did, new_owner_as_address).await
+    }
+
+    /// permanently deactivate the identity by transferring ownership to the zero address
+    pub async fn deactivate(&self, did: &Did) -> Result<RegistryReceipt, DidEthError> {
+        self.transfer_owner(did, Address::zero()).await
+    }
+
+    async fn transfer_owner(
+        &self,
+        did: &Did,
+        new_owner: Address,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let id_as_address = self.resolver.identity(did)?;
+        let signer_address = self.sender();
+        self.ensure_active(id_as_address).await?;
+        let current_owner = self.contract.identity_owner(id_as_address).call().await?;
+        if current_owner != signer_address {
+            return Err(DidEthError::NotOwner {
+                identity: id_as_address,
+                owner: current_owner,
+                signer: signer_address,
+            });
+        }
+        tracing::info!("Transferring {did} from {current_owner:#x} to {new_owner:#x}");
+        let tx = self.contract.change_owner(id_as_address, new_owner);
+        let receipt = self.send_and_confirm(tx).await?;
+        // the registry reports the identity as its own owner once ownership is cleared
+        let expected_owner = if new_owner.is_zero() {
+            id_as_address
+        } else {
+            new_owner
+        };
+        let owner = self.contract.identity_owner(id_as_address).call().await?;
+        if owner != expected_owner {
+            return Err(DidEthError::OwnerNotChanged {
+                identity: id_as_address,
+                owner,
+                expected: expected_owner,
+            });
+        }
+        Ok(receipt)
+    }
+
+    /// relay a meta transaction signed by the identity owner, gas is paid by this registry's signer
+    pub async fn submit_signed(
+        &self,
+        signed: &SignedMetaTransaction,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let registry_address = self.contract.address();
+        if signed.registry != registry_address {
+            return Err(DidEthError::InvalidMetaTransaction(format!(
+                "signed for registry {:#x}, connected to {registry_address:#x}",
+                signed.registry
+            )));
         }
-        let mut revocation_set = HashSet::<String>::new();
-        while let Ok(prev_change) = prev_change_result {
-            let prev_change = U64::from(prev_change.as_u64());
-            if prev_change == U64::zero() {
-                tracing::debug!("last change");
-                break;
+        signed.operation.validate(signed.identity)?;
+        let signer = signed.signer()?;
+        self.ensure_active(signed.identity).await?;
+        let owner = self.contract.identity_owner(signed.identity).call().await?;
+        if signer != owner {
+            return Err(DidEthError::NotOwner {
+                identity: signed.identity,
+                owner,
+                signer,
+            });
+        }
+        let nonce = self.contract.nonce(owner).call().await?;
+        if signed.nonce != nonce {
+            return Err(DidEthError::InvalidMetaTransaction(format!(
+                "nonce {} does not match registry nonce {nonce}",
+                signed.nonce
+            )));
+        }
+        let identity = signed.identity;
+        let (v, r, s) = (
+            signed.v,
+            signed.r.to_fixed_bytes(),
+            signed.s.to_fixed_bytes(),
+        );
+        let tx = match &signed.operation {
+            Operation::SetAttribute {
+                name,
+                value,
+                validity,
+            } => self.contract.set_attribute_signed(
+                identity,
+                v,
+                r,
+                s,
+                name_to_bytes32(name)?,
+                value.clone(),
+                *validity,
+            ),
+            Operation::RevokeAttribute { name, value } => self.contract.revoke_attribute_signed(
+                identity,
+                v,
+                r,
+                s,
+                name_to_bytes32(name)?,
+                value.clone(),
+            ),
+            Operation::AddDelegate {
+                delegate_type,
+                delegate,
+                validity,
+            } => self.contract.add_delegate_signed(
+                identity,
+                v,
+                r,
+                s,
+                delegate_type.to_bytes32(),
+                *delegate,
+                *validity,
+            ),
+            Operation::RevokeDelegate {
+                delegate_type,
+                delegate,
+            } => self.contract.revoke_delegate_signed(
+                identity,
+                v,
+                r,
+                s,
+                delegate_type.to_bytes32(),
+                *delegate,
+            ),
+            Operation::ChangeOwner { .. } | Operation::Deactivate => {
+                let new_owner = signed.operation.new_owner().unwrap_or_default();
+                self.contract
+                    .change_owner_signed(identity, v, r, s, new_owner)
             }
-            tracing::info!("prev_change: {prev_change}");
-            let id_topic = [H256::from(id_as_address)];
-            let contract_addr = DID_ETH_REGISTRY.parse::<Address>().unwrap();
-            let filter = Filter::new()
-                .from_block(prev_change)
-                .to_block(prev_change)
-                .event("DIDAttributeChanged(address,bytes32,bytes,uint256,uint256)")
-                .address(vec![contract_addr])
-                .topic1(id_topic.to_vec());
-
-            tracing::debug!("filter: {:?}", filter);
-            let logs = self.signer.get_logs(&filter).await;
-            if let Ok(logs) = logs {
-                if logs.is_empty() {
-                    tracing::debug!("no logs");
-                    break;
-                }
-                for log in logs.iter() {
-                    // topic 0 is the event signature
-                    tracing::debug!("log: {:?}", log);
-                    let owner_id = Address::from(log.topics[0]);
-                    tracing::debug!("owner: {:?}", owner_id);
-
-                    let param_result = self.decode_did_attribute_changed_param(log.data.to_vec());
-                    if let Ok(param) = param_result {
-                        let mut hasher = Sha3_256::default();
-                        tracing::debug!("param: {:?}", param);
-                        let name_fixed = param[0].clone().into_fixed_bytes().unwrap();
-                        hasher.update(name_fixed.as_slice());
-                        let name_utf8 = String::from_utf8(name_fixed.to_vec()).unwrap();
-                        let attribute_name = self.as_pretty_string(&name_utf8).unwrap();
-                        tracing::info!("attribute name: {attribute_name}");
-                        let attribute_value = param[1].clone().into_string().unwrap();
-                        hasher.update(attribute_value.as_bytes());
-                        tracing::info!("attribute value: {attribute_value}");
-                        let validity = param[2].clone().into_uint().unwrap();
-                        tracing::info!("valid until: {validity}");
-                        let log_prev_change = param[3].clone().into_uint().unwrap();
-                        prev_change_result = Ok(log_prev_change);
-                        let digest_buffer = hasher.finalize();
-                        let digest = hex::encode(digest_buffer);
-                        if block_timestamp < validity && !revocation_set.contains(&digest.clone()) {
-                            result_vec.push((attribute_name.clone(), attribute_value));
-                        } else if validity == U256::zero() {
-                            tracing::info!("revoked");
-                            revocation_set.insert(digest.clone());
-                            break;
-                        }
-                    } else {
-                        tracing::error!("Error decoding param: {:?}", param_result);
-                        break;
-                    }
-                }
-            } else {
-                tracing::error!("Error getting logs: {:?}", logs);
-                break;
-            };
+        };
+        tracing::info!(
+            "Relaying {} for {identity:#x}",
+            signed.operation.function_name()
+        );
+        self.send_and_confirm(tx).await
+    }
+
+    /// send the transaction and wait for the configured confirmations
+    async fn send_and_confirm(
+        &self,
+        tx: ContractCall<M, ()>,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let config = self.config();
+        let pending = tx.send().await?;
+        let tx_hash = pending.tx_hash();
+        tracing::info!("Transaction sent: {tx_hash:#x}");
+        let receipt = within(
+            config.confirmation_timeout,
+            pending.confirmations(config.confirmations),
+        )
+        .await
+        .ok_or(DidEthError::ConfirmationTimeout(tx_hash))?
+        .map_err(DidEthError::transport)?
+        .ok_or(DidEthError::TransactionDropped(tx_hash))?;
+        if receipt.status != Some(U64::one()) {
+            return Err(DidEthError::TransactionFailed(tx_hash));
         }
-        Ok(result_vec)
+        RegistryReceipt::from_receipt(&receipt, self.contract.address())
     }
+}
 
-    pub fn wallet_address(&self) -> String {
-        self.signer.address().to_string()
-    }
-
-    fn decode_did_attribute_changed_param(&self, data: Vec<u8>) -> Result<Vec<Token>, Error> {
-        let param = [
-            ethabi::ParamType::FixedBytes(32),
-            ethabi::ParamType::String,
-            ethabi::ParamType::Uint(256),
-            ethabi::ParamType::Uint(256),
-        ];
-        let decoded = ethabi::decode(&param, &data)?;
-        Ok(decoded)
-    }
-
-    fn as_pretty_string(&self, fixed: &str) -> Result<String, Error> {
-        let mut result = String::new();
-        fixed
-            .chars()
-            .take_while(|c| !char::is_control(*c))
-            .for_each(|c| result.push(c));
-        Ok(result)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{abi::AbiEncode, contract::EthEvent, providers::MockProvider, types::Bytes};
+
+    const IDENTITY: &str = "did:ethr:sepolia:0x6CEb0bF1f28ca4165d5C0A04f61DC733987eD6ad";
+
+    fn identity() -> Did {
+        IDENTITY.parse().unwrap()
+    }
+
+    // mocked responses are served last in, first out
+    async fn mocked_registry() -> (DidEthRegistry<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(SEPOLIA_CHAIN_ID)).unwrap();
+        let config = RegistryConfig::for_network(Network::Sepolia);
+        let registry = DidEthRegistry::from_client(Arc::new(provider), config)
+            .await
+            .unwrap();
+        (registry, mock)
+    }
+
+    async fn mocked_signing_registry() -> (
+        DidEthRegistry<SignerMiddleware<Provider<MockProvider>, WalletType>>,
+        MockProvider,
+    ) {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(SEPOLIA_CHAIN_ID)).unwrap();
+        let wallet =
+            wallet_from_key("0x0000000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+        let client = SignerMiddleware::new(provider, wallet);
+        let config = RegistryConfig::for_network(Network::Sepolia);
+        let registry = DidEthRegistry::from_client(Arc::new(client), config)
+            .await
+            .unwrap();
+        (registry, mock)
+    }
+
+    #[tokio::test]
+    async fn test_read_only_owner() {
+        let (registry, mock) = mocked_registry().await;
+        let owner = Address::repeat_byte(0x42);
+        mock.push::<Bytes, Bytes>(owner.encode().into()).unwrap();
+        let owner_id = registry.owner(&identity()).await.unwrap();
+        assert_eq!(owner_id.parse::<Address>().unwrap(), owner);
+    }
+
+    #[tokio::test]
+    async fn test_deactivated_rejects_writes() {
+        let (registry, mock) = mocked_signing_registry().await;
+        let identity = registry.sender();
+
+        let data = ethers::abi::encode(&[
+            ethers::abi::Token::Address(Address::zero()),
+            ethers::abi::Token::Uint(U256::zero()),
+        ]);
+        let deactivation = ethers::types::Log {
+            address: registry.registry_address(),
+            topics: vec![DidownerChangedFilter::signature(), identity.into()],
+            data: data.into(),
+            block_number: Some(U64::from(3)),
+            ..Default::default()
+        };
+        mock.push::<Vec<ethers::types::Log>, Vec<ethers::types::Log>>(vec![deactivation])
+            .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(3).encode().into())
+            .unwrap();
+        mock.push::<Bytes, Bytes>(Address::zero().encode().into())
+            .unwrap();
+        assert!(matches!(
+            registry
+                .set_attribute("name".to_string(), "value".to_string())
+                .await,
+            Err(DidEthError::Deactivated(deactivated)) if deactivated == identity
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_input() {
+        let (registry, _mock) = mocked_registry().await;
+        assert!(matches!(
+            registry
+                .valid_delegate(
+                    &identity(),
+                    DelegateType::VeriKey,
+                    "not an address".to_string()
+                )
+                .await,
+            Err(DidEthError::InvalidAddress(_))
+        ));
+        let mainnet = Did::new(MAINNET_CHAIN_ID, identity().address());
+        assert!(matches!(
+            registry.owner(&mainnet).await,
+            Err(DidEthError::ChainMismatch { .. })
+        ));
+
+        let (registry, _mock) = mocked_signing_registry().await;
+        assert!(matches!(
+            registry
+                .change_owner(&identity(), format!("{:#x}", Address::zero()))
+                .await,
+            Err(DidEthError::ZeroAddressOwner(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_chain_id_mismatch() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(MAINNET_CHAIN_ID)).unwrap();
+        let config = RegistryConfig::for_network(Network::Sepolia);
+        assert!(matches!(
+            DidEthRegistry::from_client(Arc::new(provider), config).await,
+            Err(DidEthError::ChainMismatch { .. })
+        ));
     }
 }
@@ -0,0 +1,749 @@
+use async_trait::async_trait;
+use devchain::{BLOCK_TIME, GENESIS_TIMESTAMP};
+use ethers::{
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    active_attributes, active_delegates,
+    error::{name_to_bytes32, parse_address},
+    DIDRegistryEvents, DelegateType, Did, DidEthError, DidattributeChangedFilter,
+    DiddelegateChangedFilter, DidownerChangedFilter, HistoryEntry, Network, Operation,
+    RegistryBackend, RegistryConfig, RegistryEvent, RegistryReceipt, SignedMetaTransaction,
+    DEV_CHAIN_ID,
+};
+
+/// Registry storage and events, laid out like the ERC-1056 contract
+#[derive(Debug, Default)]
+struct RegistryState {
+    block_number: u64,
+    timestamp: u64,
+    owners: HashMap<Address, Address>,
+    delegates: HashMap<(Address, [u8; 32], Address), U256>,
+    changed: HashMap<Address, u64>,
+    nonces: HashMap<Address, U256>,
+    events: BTreeMap<u64, Vec<HistoryEntry>>,
+}
+
+impl RegistryState {
+    fn identity_owner(&self, identity: Address) -> Address {
+        match self.owners.get(&identity) {
+            Some(owner) if !owner.is_zero() => *owner,
+            _ => identity,
+        }
+    }
+
+    fn is_deactivated(&self, identity: Address) -> bool {
+        self.owners.get(&identity).is_some_and(Address::is_zero)
+    }
+
+    fn ensure_active(&self, identity: Address) -> Result<(), DidEthError> {
+        if self.is_deactivated(identity) {
+            return Err(DidEthError::Deactivated(identity));
+        }
+        Ok(())
+    }
+
+    fn mine(&mut self, seconds: u64) {
+        self.block_number += 1;
+        self.timestamp += seconds;
+    }
+
+    /// execute `operation` for `identity` as the registry would for a transaction from `actor`
+    fn apply(
+        &mut self,
+        identity: Address,
+        actor: Address,
+        operation: &Operation,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        if actor != self.identity_owner(identity) {
+            return Err(DidEthError::ContractRevert {
+                reason: "bad_actor".to_string(),
+            });
+        }
+        // names the contract call could not encode never reach a block
+        if let Operation::SetAttribute { name, .. } | Operation::RevokeAttribute { name, .. } =
+            operation
+        {
+            name_to_bytes32(name)?;
+        }
+        // every transaction is mined in its own block
+        let now = U256::from(self.timestamp + BLOCK_TIME);
+        let valid_to = match operation {
+            Operation::SetAttribute { validity, .. } | Operation::AddDelegate { validity, .. } => {
+                // the contract's checked arithmetic reverts with Panic(0x11)
+                now.checked_add(*validity)
+                    .ok_or_else(|| DidEthError::ContractRevert {
+                        reason: "arithmetic overflow".to_string(),
+                    })?
+            }
+            _ => now,
+        };
+        self.mine(BLOCK_TIME);
+        let previous_change = U256::from(self.changed.get(&identity).copied().unwrap_or_default());
+        let event: DIDRegistryEvents = match operation {
+            Operation::SetAttribute { name, value, .. } => DidattributeChangedFilter {
+                identity,
+                name: name_to_bytes32(name)?,
+                value: value.clone(),
+                valid_to,
+                previous_change,
+            }
+            .into(),
+            Operation::RevokeAttribute { name, value } => DidattributeChangedFilter {
+                identity,
+                name: name_to_bytes32(name)?,
+                value: value.clone(),
+                valid_to: U256::zero(),
+                previous_change,
+            }
+            .into(),
+            Operation::AddDelegate {
+                delegate_type,
+                delegate,
+                ..
+            } => {
+                self.delegates
+                    .insert((identity, delegate_type.to_bytes32(), *delegate), valid_to);
+                DiddelegateChangedFilter {
+                    identity,
+                    delegate_type: delegate_type.to_bytes32(),
+                    delegate: *delegate,
+                    valid_to,
+                    previous_change,
+                }
+                .into()
+            }
+            Operation::RevokeDelegate {
+                delegate_type,
+                delegate,
+            } => {
+                self.delegates
+                    .insert((identity, delegate_type.to_bytes32(), *delegate), now);
+                DiddelegateChangedFilter {
+                    identity,
+                    delegate_type: delegate_type.to_bytes32(),
+                    delegate: *delegate,
+                    valid_to: now,
+                    previous_change,
+                }
+                .into()
+            }
+            Operation::ChangeOwner { .. } | Operation::Deactivate => {
+                let new_owner = operation.new_owner().unwrap_or_default();
+                self.owners.insert(identity, new_owner);
+                DidownerChangedFilter {
+                    identity,
+                    owner: new_owner,
+                    previous_change,
+                }
+                .into()
+            }
+        };
+        self.changed.insert(identity, self.block_number);
+        self.events
+            .entry(self.block_number)
+            .or_default()
+            .push(HistoryEntry {
+                block_number: self.block_number,
+                log_index: 0,
+                event: event.clone(),
+            });
+        Ok(self.receipt(event))
+    }
+
+    fn receipt(&self, event: DIDRegistryEvents) -> RegistryReceipt {
+        let mut receipt = RegistryReceipt {
+            transaction_hash: H256::from(keccak256(self.block_number.to_be_bytes())),
+            block_number: Some(self.block_number),
+            gas_used: None,
+            effective_gas_price: None,
+            status: Some(1),
+            attribute_changed: None,
+            delegate_changed: None,
+            owner_changed: None,
+        };
+        match event.into() {
+            RegistryEvent::AttributeChanged(event) => receipt.attribute_changed = Some(event),
+            RegistryEvent::DelegateChanged(event) => receipt.delegate_changed = Some(event),
+            RegistryEvent::OwnerChanged(event) => receipt.owner_changed = Some(event),
+        }
+        receipt
+    }
+
+    /// follow the `previousChange` chain back from `changed(identity)`
+    fn history(&self, identity: Address) -> Result<Vec<HistoryEntry>, DidEthError> {
+        let mut history = Vec::new();
+        let mut prev_change = self.changed.get(&identity).copied().unwrap_or_default();
+        while prev_change != 0 {
+            let block = prev_change;
+            let entries = self
+                .events
+                .get(&block)
+                .map(|entries| {
+                    entries
+                        .iter()
+                        .filter(|entry| entry.registry_event().identity() == identity)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let Some(first) = entries.first() else {
+                return Err(DidEthError::MissingHistory(block));
+            };
+            prev_change = first.previous_change().as_u64();
+            history.extend(entries.into_iter().rev().cloned());
+        }
+        history.reverse();
+        Ok(history)
+    }
+}
+
+/// An in-process ERC-1056 registry for tests that run without a chain
+///
+/// Storage, events, nonces and `previousChange` linkage follow the deployed
+/// contract, and the client side checks of `DidEthRegistry` are applied before
+/// each write.  Every write is mined in its own block, `BLOCK_TIME` seconds after
+/// the last.  Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct InMemoryRegistry {
+    state: Arc<Mutex<RegistryState>>,
+    config: RegistryConfig,
+    sender: Address,
+}
+
+impl InMemoryRegistry {
+    /// a registry on the dev network with writes sent from `sender`
+    pub fn new(sender: Address) -> Self {
+        Self::from_config(RegistryConfig::for_network(Network::Dev), sender)
+    }
+
+    pub fn from_config(config: RegistryConfig, sender: Address) -> Self {
+        let state = RegistryState {
+            timestamp: GENESIS_TIMESTAMP,
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            config,
+            sender,
+        }
+    }
+
+    /// the same registry with writes sent from another account
+    pub fn with_sender(&self, sender: Address) -> Self {
+        Self {
+            sender,
+            ..self.clone()
+        }
+    }
+
+    pub fn config(&self) -> &RegistryConfig {
+        &self.config
+    }
+
+    pub fn registry_address(&self) -> Address {
+        self.config.registry
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.lock().block_number
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.lock().timestamp
+    }
+
+    /// mine an empty block
+    pub fn mine(&self) {
+        self.lock().mine(BLOCK_TIME);
+    }
+
+    /// mine an empty block `seconds` after the latest one
+    pub fn advance_time(&self, seconds: u64) {
+        self.lock().mine(seconds);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        self.state.lock().expect("registry state")
+    }
+
+    fn identity(&self, did: &Did) -> Result<Address, DidEthError> {
+        let chain_id = self.chain_id();
+        if did.chain_id() != chain_id {
+            return Err(DidEthError::ChainMismatch {
+                connected: chain_id.into(),
+                expected: did.chain_id(),
+            });
+        }
+        Ok(did.address())
+    }
+
+    /// a direct write by the sender on its own identity
+    fn write(&self, operation: Operation) -> Result<RegistryReceipt, DidEthError> {
+        let mut state = self.lock();
+        state.ensure_active(self.sender)?;
+        state.apply(self.sender, self.sender, &operation)
+    }
+
+    fn transfer_owner(
+        &self,
+        did: &Did,
+        new_owner: Address,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let identity = self.identity(did)?;
+        let mut state = self.lock();
+        state.ensure_active(identity)?;
+        let owner = state.identity_owner(identity);
+        if owner != self.sender {
+            return Err(DidEthError::NotOwner {
+                identity,
+                owner,
+                signer: self.sender,
+            });
+        }
+        state.apply(identity, self.sender, &Operation::ChangeOwner { new_owner })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl RegistryBackend for InMemoryRegistry {
+    fn chain_id(&self) -> u64 {
+        self.config.chain_id.unwrap_or(DEV_CHAIN_ID)
+    }
+
+    fn sender(&self) -> Address {
+        self.sender
+    }
+
+    async fn owner(&self, did: &Did) -> Result<String, DidEthError> {
+        let identity = self.identity(did)?;
+        let state = self.lock();
+        let owner = if state.is_deactivated(identity) {
+            Address::zero()
+        } else {
+            state.identity_owner(identity)
+        };
+        Ok(format!("{owner:#032x}"))
+    }
+
+    async fn is_deactivated(&self, did: &Did) -> Result<bool, DidEthError> {
+        let identity = self.identity(did)?;
+        Ok(self.lock().is_deactivated(identity))
+    }
+
+    async fn valid_delegate(
+        &self,
+        did: &Did,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<bool, DidEthError> {
+        let identity = self.identity(did)?;
+        let delegate = parse_address(&delegate)?;
+        let state = self.lock();
+        // validDelegate requires the validity to extend past the latest block
+        let valid_to = state
+            .delegates
+            .get(&(identity, delegate_type.to_bytes32(), delegate))
+            .copied()
+            .unwrap_or_default();
+        Ok(valid_to > U256::from(state.timestamp))
+    }
+
+    async fn nonce(&self, did: &Did) -> Result<U256, DidEthError> {
+        let identity = self.identity(did)?;
+        let state = self.lock();
+        let owner = state.identity_owner(identity);
+        Ok(state.nonces.get(&owner).copied().unwrap_or_default())
+    }
+
+    async fn attribute(&self, did: &Did) -> Result<Vec<(String, String)>, DidEthError> {
+        let identity = self.identity(did)?;
+        let state = self.lock();
+        let history = state.history(identity)?;
+        Ok(active_attributes(&history, state.timestamp.into()))
+    }
+
+    async fn delegates(&self, did: &Did) -> Result<Vec<(DelegateType, Address)>, DidEthError> {
+        let identity = self.identity(did)?;
+        let state = self.lock();
+        let history = state.history(identity)?;
+        Ok(active_delegates(&history, state.timestamp.into()))
+    }
+
+    async fn history(&self, did: &Did) -> Result<Vec<HistoryEntry>, DidEthError> {
+        let identity = self.identity(did)?;
+        self.lock().history(identity)
+    }
+
+    async fn set_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        self.write(Operation::SetAttribute {
+            name,
+            value: Bytes::from(value.into_bytes()),
+            validity: U256::from(self.config.attribute_lifetime),
+        })
+    }
+
+    async fn revoke_attribute(
+        &self,
+        name: String,
+        value: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        self.write(Operation::RevokeAttribute {
+            name,
+            value: Bytes::from(value.into_bytes()),
+        })
+    }
+
+    async fn add_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+        validity: u64,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        self.write(Operation::AddDelegate {
+            delegate_type,
+            delegate: parse_address(&delegate)?,
+            validity: U256::from(validity),
+        })
+    }
+
+    async fn revoke_delegate(
+        &self,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        self.write(Operation::RevokeDelegate {
+            delegate_type,
+            delegate: parse_address(&delegate)?,
+        })
+    }
+
+    async fn change_owner(
+        &self,
+        did: &Did,
+        new_owner: String,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let new_owner = parse_address(&new_owner)?;
+        if new_owner.is_zero() {
+            return Err(DidEthError::ZeroAddressOwner(did.address()));
+        }
+        self.transfer_owner(did, new_owner)
+    }
+
+    async fn deactivate(&self, did: &Did) -> Result<RegistryReceipt, DidEthError> {
+        self.transfer_owner(did, Address::zero())
+    }
+
+    async fn submit_signed(
+        &self,
+        signed: &SignedMetaTransaction,
+    ) -> Result<RegistryReceipt, DidEthError> {
+        let registry = self.registry_address();
+        if signed.registry != registry {
+            return Err(DidEthError::InvalidMetaTransaction(format!(
+                "signed for registry {:#x}, connected to {registry:#x}",
+                signed.registry
+            )));
+        }
+        signed.operation.validate(signed.identity)?;
+        let signer = signed.signer()?;
+        let mut state = self.lock();
+        state.ensure_active(signed.identity)?;
+        let owner = state.identity_owner(signed.identity);
+        if signer != owner {
+            return Err(DidEthError::NotOwner {
+                identity: signed.identity,
+                owner,
+                signer,
+            });
+        }
+        let nonce = state.nonces.get(&owner).copied().unwrap_or_default();
+        if signed.nonce != nonce {
+            return Err(DidEthError::InvalidMetaTransaction(format!(
+                "nonce {} does not match registry nonce {nonce}",
+                signed.nonce
+            )));
+        }
+        // a reverted transaction leaves the nonce unused
+        let receipt = state.apply(signed.identity, owner, &signed.operation)?;
+        state.nonces.insert(owner, nonce + 1);
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign_bundle;
+    use ethers::{prelude::LocalWallet, signers::Signer};
+
+    // anvil dev account 0
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const SERVICE: &str = "did/svc/MessagingService";
+    const ENDPOINT: &str = "https://example.com/messaging";
+
+    fn owner() -> Address {
+        KEY.parse::<LocalWallet>().unwrap().address()
+    }
+
+    fn did(identity: Address) -> Did {
+        Did::new(DEV_CHAIN_ID, identity)
+    }
+
+    #[test]
+    fn test_dev_chain_id() {
+        assert_eq!(DEV_CHAIN_ID, devchain::DEV_CHAIN_ID);
+    }
+
+    #[tokio::test]
+    async fn test_previous_change_linkage() {
+        let registry = InMemoryRegistry::new(owner());
+        let first = registry
+            .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+            .await
+            .unwrap();
+        registry.mine();
+        let second = registry
+            .add_delegate(
+                DelegateType::VeriKey,
+                format!("{:#x}", Address::random()),
+                60,
+            )
+            .await
+            .unwrap();
+        assert_eq!(first.block_number, Some(1));
+        assert_eq!(second.block_number, Some(3));
+        assert_eq!(
+            first.attribute_changed.unwrap().previous_change,
+            U256::zero()
+        );
+        assert_eq!(
+            second.delegate_changed.unwrap().previous_change,
+            U256::from(1)
+        );
+
+        let history = registry.history(&did(owner())).await.unwrap();
+        let blocks = history
+            .iter()
+            .map(|entry| entry.block_number)
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![1, 3]);
+        // other identities have no history
+        let other = did(Address::random());
+        assert!(registry.history(&other).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_attribute_validity() {
+        let registry = InMemoryRegistry::from_config(
+            RegistryConfig::for_network(Network::Dev).with_attribute_lifetime(100),
+            owner(),
+        );
+        registry
+            .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            registry.attribute(&did(owner())).await.unwrap(),
+            vec![(SERVICE.to_string(), ENDPOINT.to_string())]
+        );
+        registry.advance_time(101);
+        assert!(registry.attribute(&did(owner())).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delegate_validity() {
+        let registry = InMemoryRegistry::new(owner());
+        let delegate = Address::random();
+        registry
+            .add_delegate(DelegateType::SigAuth, format!("{delegate:#x}"), 3600)
+            .await
+            .unwrap();
+        assert!(registry
+            .valid_delegate(
+                &did(owner()),
+                DelegateType::SigAuth,
+                format!("{delegate:#x}")
+            )
+            .await
+            .unwrap());
+        registry
+            .revoke_delegate(DelegateType::SigAuth, format!("{delegate:#x}"))
+            .await
+            .unwrap();
+        // the contract treats a delegate revoked at the latest timestamp as invalid,
+        // event based resolution keeps it until the next block
+        assert!(!registry
+            .valid_delegate(
+                &did(owner()),
+                DelegateType::SigAuth,
+                format!("{delegate:#x}")
+            )
+            .await
+            .unwrap());
+        assert_eq!(
+            registry.delegates(&did(owner())).await.unwrap(),
+            vec![(DelegateType::SigAuth, delegate)]
+        );
+        registry.mine();
+        assert!(registry.delegates(&did(owner())).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_change_owner() {
+        let registry = InMemoryRegistry::new(owner());
+        let new_owner = Address::random();
+        registry
+            .change_owner(&did(owner()), format!("{new_owner:#x}"))
+            .await
+            .unwrap();
+        assert_eq!(
+            registry
+                .owner(&did(owner()))
+                .await
+                .unwrap()
+                .parse::<Address>()
+                .unwrap(),
+            new_owner
+        );
+        // the former owner can no longer change its identity
+        assert!(matches!(
+            registry
+                .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+                .await,
+            Err(DidEthError::ContractRevert { reason }) if reason == "bad_actor"
+        ));
+        assert!(matches!(
+            registry.deactivate(&did(owner())).await,
+            Err(DidEthError::NotOwner { owner, .. }) if owner == new_owner
+        ));
+        let new_owner_registry = registry.with_sender(new_owner);
+        new_owner_registry.deactivate(&did(owner())).await.unwrap();
+        assert!(registry.is_deactivated(&did(owner())).await.unwrap());
+        assert!(registry
+            .owner(&did(owner()))
+            .await
+            .unwrap()
+            .parse::<Address>()
+            .unwrap()
+            .is_zero());
+        assert!(matches!(
+            new_owner_registry
+                .change_owner(&did(owner()), format!("{new_owner:#x}"))
+                .await,
+            Err(DidEthError::Deactivated(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_signed_deactivation() {
+        let relay = InMemoryRegistry::new(Address::random());
+        let identity = owner();
+        let bundle = sign_bundle(
+            KEY,
+            relay.registry_address(),
+            identity,
+            U256::zero(),
+            vec![Operation::Deactivate],
+        )
+        .unwrap();
+        let mut zero_owner = bundle[0].clone();
+        zero_owner.operation = Operation::ChangeOwner {
+            new_owner: Address::zero(),
+        };
+        assert!(matches!(
+            relay.submit_signed(&zero_owner).await,
+            Err(DidEthError::ZeroAddressOwner(_))
+        ));
+        assert!(!relay.is_deactivated(&did(identity)).await.unwrap());
+
+        relay.submit_signed(&bundle[0]).await.unwrap();
+        assert!(relay.is_deactivated(&did(identity)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_validity_overflow_reverts() {
+        let relay = InMemoryRegistry::new(Address::random());
+        let identity = owner();
+        let operations = vec![
+            Operation::SetAttribute {
+                name: SERVICE.to_string(),
+                value: ENDPOINT.as_bytes().to_vec().into(),
+                validity: U256::MAX,
+            },
+            Operation::AddDelegate {
+                delegate_type: DelegateType::SigAuth,
+                delegate: Address::random(),
+                validity: U256::MAX,
+            },
+        ];
+        for operation in operations {
+            let bundle = sign_bundle(
+                KEY,
+                relay.registry_address(),
+                identity,
+                U256::zero(),
+                vec![operation],
+            )
+            .unwrap();
+            assert!(matches!(
+                relay.submit_signed(&bundle[0]).await,
+                Err(DidEthError::ContractRevert { .. })
+            ));
+        }
+        // nothing was mined and the nonce is still unused
+        assert_eq!(relay.block_number(), 0);
+        assert_eq!(relay.nonce(&did(identity)).await.unwrap(), U256::zero());
+        assert!(relay.history(&did(identity)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_signed_nonces() {
+        let relay = InMemoryRegistry::new(Address::random());
+        let identity = owner();
+        let nonce = relay.nonce(&did(identity)).await.unwrap();
+        assert_eq!(nonce, U256::zero());
+        let bundle = sign_bundle(
+            KEY,
+            relay.registry_address(),
+            identity,
+            nonce,
+            vec![
+                Operation::SetAttribute {
+                    name: SERVICE.to_string(),
+                    value: ENDPOINT.as_bytes().to_vec().into(),
+                    validity: U256::from(3600),
+                },
+                Operation::RevokeAttribute {
+                    name: SERVICE.to_string(),
+                    value: ENDPOINT.as_bytes().to_vec().into(),
+                },
+            ],
+        )
+        .unwrap();
+        // out of order submission is rejected
+        assert!(matches!(
+            relay.submit_signed(&bundle[1]).await,
+            Err(DidEthError::InvalidMetaTransaction(_))
+        ));
+        relay.submit_signed(&bundle[0]).await.unwrap();
+        assert_eq!(relay.attribute(&did(identity)).await.unwrap().len(), 1);
+        relay.submit_signed(&bundle[1]).await.unwrap();
+        assert!(relay.attribute(&did(identity)).await.unwrap().is_empty());
+        assert_eq!(relay.nonce(&did(identity)).await.unwrap(), U256::from(2));
+        assert!(matches!(
+            relay.submit_signed(&bundle[0]).await,
+            Err(DidEthError::InvalidMetaTransaction(_))
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+
+use crate::DidEthError;
+
+/// Options for resolving a DID document at a point in history
+///
+/// `version_id` is a block number, `version_time` an instant.  When both are set
+/// `version_id` is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolutionOptions {
+    pub version_id: Option<u64>,
+    pub version_time: Option<DateTime<Utc>>,
+}
+
+impl ResolutionOptions {
+    /// the document as of the latest block
+    pub fn latest() -> Self {
+        Self::default()
+    }
+
+    pub fn with_version_id(mut self, version_id: u64) -> Self {
+        self.version_id = Some(version_id);
+        self
+    }
+
+    pub fn with_version_time(mut self, version_time: DateTime<Utc>) -> Self {
+        self.version_time = Some(version_time);
+        self
+    }
+
+    /// parse an ISO 8601 `versionTime` such as `2023-11-21T17:05:00Z`
+    pub fn parse_version_time(version_time: &str) -> Result<DateTime<Utc>, DidEthError> {
+        DateTime::parse_from_rfc3339(version_time)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|_| DidEthError::InvalidVersionTime(version_time.to_string()))
+    }
+
+    pub fn is_latest(&self) -> bool {
+        self.version_id.is_none() && self.version_time.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_time() {
+        let time = ResolutionOptions::parse_version_time("2023-11-21T17:05:00Z").unwrap();
+        assert_eq!(time.timestamp(), 1_700_586_300);
+        let offset = ResolutionOptions::parse_version_time("2023-11-21T18:05:00+01:00").unwrap();
+        assert_eq!(offset, time);
+        assert!(matches!(
+            ResolutionOptions::parse_version_time("yesterday"),
+            Err(DidEthError::InvalidVersionTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(ResolutionOptions::latest().is_latest());
+        let options = ResolutionOptions::latest().with_version_id(42);
+        assert_eq!(options.version_id, Some(42));
+        assert!(!options.is_latest());
+    }
+}
@@ -0,0 +1,122 @@
+use ethers::{
+    contract::EthLogDecode,
+    types::{Address, TransactionReceipt, H256, U256},
+};
+
+use crate::{AttributeChanged, DIDRegistryEvents, DelegateChanged, DidEthError, OwnerChanged};
+
+/// The outcome of a registry transaction with the registry events it emitted
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryReceipt {
+    pub transaction_hash: H256,
+    pub block_number: Option<u64>,
+    pub gas_used: Option<U256>,
+    pub effective_gas_price: Option<U256>,
+    /// 1 for success, 0 for failure
+    pub status: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribute_changed: Option<AttributeChanged>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegate_changed: Option<DelegateChanged>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_changed: Option<OwnerChanged>,
+}
+
+impl RegistryReceipt {
+    /// decode the events `registry` emitted in this transaction
+    pub fn from_receipt(
+        receipt: &TransactionReceipt,
+        registry: Address,
+    ) -> Result<Self, DidEthError> {
+        let mut registry_receipt = Self {
+            transaction_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.map(|block| block.as_u64()),
+            gas_used: receipt.gas_used,
+            effective_gas_price: receipt.effective_gas_price,
+            status: receipt.status.map(|status| status.as_u64()),
+            attribute_changed: None,
+            delegate_changed: None,
+            owner_changed: None,
+        };
+        for log in receipt.logs.iter().filter(|log| log.address == registry) {
+            let event = DIDRegistryEvents::decode_log(&log.clone().into())
+                .map_err(|err| DidEthError::MalformedLog(format!("{err}: {log:?}")))?;
+            tracing::debug!("receipt event: {event}");
+            match event {
+                DIDRegistryEvents::DidattributeChangedFilter(event) => {
+                    registry_receipt.attribute_changed = Some(event.into())
+                }
+                DIDRegistryEvents::DiddelegateChangedFilter(event) => {
+                    registry_receipt.delegate_changed = Some(event.into())
+                }
+                DIDRegistryEvents::DidownerChangedFilter(event) => {
+                    registry_receipt.owner_changed = Some(event.into())
+                }
+            }
+        }
+        Ok(registry_receipt)
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == Some(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DidattributeChangedFilter;
+    use ethers::{
+        abi::{encode, Token},
+        contract::EthEvent,
+        types::{Log, U64},
+        utils::format_bytes32_string,
+    };
+
+    #[test]
+    fn test_from_receipt() {
+        let registry = Address::repeat_byte(0x10);
+        let identity = Address::repeat_byte(0x11);
+        let data = encode(&[
+            Token::FixedBytes(format_bytes32_string("did/svc/Messaging").unwrap().to_vec()),
+            Token::Bytes(b"https://example.com".to_vec()),
+            Token::Uint(U256::from(2_000)),
+            Token::Uint(U256::from(7)),
+        ]);
+        let attribute_log = Log {
+            address: registry,
+            topics: vec![DidattributeChangedFilter::signature(), identity.into()],
+            data: data.into(),
+            ..Default::default()
+        };
+        let other_log = Log {
+            address: Address::repeat_byte(0x99),
+            topics: vec![H256::repeat_byte(0x01)],
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            transaction_hash: H256::repeat_byte(0xaa),
+            block_number: Some(U64::from(9)),
+            gas_used: Some(U256::from(50_000)),
+            effective_gas_price: Some(U256::from(1_000_000_000)),
+            status: Some(U64::one()),
+            logs: vec![other_log, attribute_log],
+            ..Default::default()
+        };
+        let registry_receipt = RegistryReceipt::from_receipt(&receipt, registry).unwrap();
+        assert!(registry_receipt.is_success());
+        assert_eq!(registry_receipt.block_number, Some(9));
+        let attribute_changed = registry_receipt.attribute_changed.clone().unwrap();
+        assert_eq!(attribute_changed.identity, identity);
+        assert_eq!(attribute_changed.name, "did/svc/Messaging");
+        assert_eq!(attribute_changed.value.as_ref(), b"https://example.com");
+        assert_eq!(attribute_changed.previous_change, U256::from(7));
+        assert!(registry_receipt.delegate_changed.is_none());
+
+        let json = serde_json::to_value(&registry_receipt).unwrap();
+        assert_eq!(json["attributeChanged"]["name"], "did/svc/Messaging");
+        assert_eq!(json["gasUsed"], "0xc350");
+        assert!(json.get("ownerChanged").is_none());
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use ethers::types::U256;
+
+use crate::{DidDocument, DidEthError, DidParseError};
+
+pub const DID_LD_JSON: &str = "application/did+ld+json";
+
+/// Error codes reported in `didResolutionMetadata`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResolutionError {
+    InvalidDid,
+    NotFound,
+    UnsupportedNetwork,
+    InternalError,
+}
+
+impl From<&DidEthError> for ResolutionError {
+    fn from(err: &DidEthError) -> Self {
+        match err {
+            DidEthError::InvalidDid(DidParseError::UnknownNetwork(_))
+            | DidEthError::ChainMismatch { .. } => ResolutionError::UnsupportedNetwork,
+            DidEthError::InvalidDid(_) | DidEthError::InvalidAddress(_) => {
+                ResolutionError::InvalidDid
+            }
+            DidEthError::BlockNotFound(_) => ResolutionError::NotFound,
+            _ => ResolutionError::InternalError,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidResolutionMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResolutionError>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Metadata about the resolved version of a DID document
+///
+/// Versions are identified by the block number of their last registry event and
+/// times are ISO 8601 block timestamps.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocumentMetadata {
+    /// the first registry event of the identity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    /// the last registry event applied to the document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    /// the first registry event after the resolved version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_version_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_update: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deactivated: Option<bool>,
+}
+
+/// The result of resolving a DID
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolutionResult {
+    pub did_resolution_metadata: DidResolutionMetadata,
+    pub did_document: Option<DidDocument>,
+    pub did_document_metadata: DidDocumentMetadata,
+}
+
+impl ResolutionResult {
+    pub fn resolved(did_document: DidDocument, did_document_metadata: DidDocumentMetadata) -> Self {
+        Self {
+            did_resolution_metadata: DidResolutionMetadata {
+                content_type: Some(DID_LD_JSON.to_string()),
+                ..Default::default()
+            },
+            did_document: Some(did_document),
+            did_document_metadata,
+        }
+    }
+
+    pub fn error(err: &DidEthError) -> Self {
+        Self {
+            did_resolution_metadata: DidResolutionMetadata {
+                error: Some(err.into()),
+                message: Some(err.to_string()),
+                ..Default::default()
+            },
+            did_document: None,
+            did_document_metadata: DidDocumentMetadata::default(),
+        }
+    }
+}
+
+/// format a block timestamp as ISO 8601 without fractional seconds
+pub(crate) fn iso_timestamp(timestamp: U256) -> String {
+    let seconds = i64::try_from(timestamp.low_u64()).unwrap_or(i64::MAX);
+    DateTime::<Utc>::from_timestamp(seconds, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    #[test]
+    fn test_error_codes() {
+        let cases = [
+            (
+                DidEthError::InvalidDid(DidParseError::InvalidFormat("did".to_string())),
+                ResolutionError::InvalidDid,
+            ),
+            (
+                DidEthError::InvalidDid(DidParseError::UnknownNetwork("goerli".to_string())),
+                ResolutionError::UnsupportedNetwork,
+            ),
+            (
+                DidEthError::ChainMismatch {
+                    connected: U256::one(),
+                    expected: 5,
+                },
+                ResolutionError::UnsupportedNetwork,
+            ),
+            (DidEthError::BlockNotFound(9), ResolutionError::NotFound),
+            (
+                DidEthError::ZeroAddressOwner(Address::zero()),
+                ResolutionError::InternalError,
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(ResolutionError::from(&err), expected, "{err}");
+        }
+    }
+
+    #[test]
+    fn test_error_result_json() {
+        let err = DidEthError::InvalidDid(DidParseError::InvalidFormat("did".to_string()));
+        let json = serde_json::to_value(ResolutionResult::error(&err)).unwrap();
+        assert_eq!(json["didResolutionMetadata"]["error"], "invalidDid");
+        assert!(json["didResolutionMetadata"]["message"].is_string());
+        assert!(json["didDocument"].is_null());
+        assert_eq!(json["didDocumentMetadata"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_iso_timestamp() {
+        assert_eq!(
+            iso_timestamp(U256::from(1_700_586_300)),
+            "2023-11-21T17:05:00Z"
+        );
+        assert_eq!(iso_timestamp(U256::zero()), "1970-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,1318 @@
+use ethers::{
+    contract::{EthEvent, EthLogDecode},
+    prelude::Provider,
+    providers::{Http, JsonRpcError, Middleware, MiddlewareError, Ws},
+    types::{Address, BlockNumber, Filter, Log, H256, U256},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use {ethers::providers::Ipc, std::path::Path};
+
+use crate::{
+    error::parse_address, resolution::iso_timestamp, DIDRegistry, DIDRegistryEvents, DelegateType,
+    Did, DidDocument, DidDocumentMetadata, DidEthError, DidattributeChangedFilter,
+    DiddelegateChangedFilter, DidownerChangedFilter, RegistryConfig, RegistryEvent,
+    ResolutionOptions, ResolutionResult,
+};
+
+/// A registry event for an identity, located by block and log index
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    pub block_number: u64,
+    pub log_index: u64,
+    pub event: DIDRegistryEvents,
+}
+
+impl HistoryEntry {
+    pub fn registry_event(&self) -> RegistryEvent {
+        self.event.clone().into()
+    }
+
+    pub(crate) fn from_log(log: &Log) -> Result<Self, DidEthError> {
+        let event = DIDRegistryEvents::decode_log(&log.clone().into())
+            .map_err(|err| DidEthError::MalformedLog(format!("{err}: {log:?}")))?;
+        let block_number = log
+            .block_number
+            .ok_or_else(|| DidEthError::MalformedLog(format!("pending log: {log:?}")))?;
+        Ok(Self {
+            block_number: block_number.as_u64(),
+            log_index: log.log_index.unwrap_or_default().as_u64(),
+            event,
+        })
+    }
+
+    pub fn previous_change(&self) -> U256 {
+        match &self.event {
+            DIDRegistryEvents::DidattributeChangedFilter(e) => e.previous_change,
+            DIDRegistryEvents::DiddelegateChangedFilter(e) => e.previous_change,
+            DIDRegistryEvents::DidownerChangedFilter(e) => e.previous_change,
+        }
+    }
+}
+
+/// A resolution with the timestamp validity was checked at
+pub(crate) struct Resolved {
+    pub result: ResolutionResult,
+    pub timestamp: U256,
+    /// the earliest `validTo` of the changes still valid at `timestamp`
+    pub expires: Option<U256>,
+}
+
+/// The history of an identity with the part applied at a requested version
+struct Versioned {
+    history: Vec<HistoryEntry>,
+    /// the number of events up to the version
+    count: usize,
+    owner: Address,
+    /// the timestamp validity periods are compared against at the version
+    now: U256,
+}
+
+impl Versioned {
+    fn events(&self) -> &[HistoryEntry] {
+        &self.history[..self.count]
+    }
+}
+
+/// Read-only access to the ERC-1056 registry, no signing key is needed
+pub struct Resolver<M> {
+    contract: DIDRegistry<M>,
+    client: Arc<M>,
+    config: RegistryConfig,
+    chain_id: u64,
+}
+
+impl Resolver<Provider<Ws>> {
+    pub async fn connect_ws(rpc_url: String, config: RegistryConfig) -> Result<Self, DidEthError> {
+        let provider = Provider::<Ws>::connect(rpc_url)
+            .await
+            .map_err(DidEthError::transport)?;
+        Self::from_client(Arc::new(provider), config).await
+    }
+}
+
+impl Resolver<Provider<Http>> {
+    pub async fn connect_http(rpc_url: &str, config: RegistryConfig) -> Result<Self, DidEthError> {
+        let provider = Provider::<Http>::try_from(rpc_url).map_err(DidEthError::transport)?;
+        Self::from_client(Arc::new(provider), config).await
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Resolver<Provider<Ipc>> {
+    /// connect through the local node's IPC socket
+    pub async fn connect_ipc(
+        path: impl AsRef<Path>,
+        config: RegistryConfig,
+    ) -> Result<Self, DidEthError> {
+        let provider = Provider::connect_ipc(path)
+            .await
+            .map_err(DidEthError::transport)?;
+        Self::from_client(Arc::new(provider), config).await
+    }
+}
+
+impl<M: Middleware + 'static> Resolver<M> {
+    pub async fn from_client(client: Arc<M>, config: RegistryConfig) -> Result<Self, DidEthError> {
+        let chain_id = client.get_chainid().await.map_err(DidEthError::transport)?;
+        tracing::info!("Connected to chain: {chain_id}");
+        config.validate_chain_id(chain_id)?;
+
+        let registry_address = config.registry;
+        tracing::info!("Registry Contract Connected: {registry_address:#x}");
+        let contract = DIDRegistry::new(registry_address, client.clone());
+        Ok(Self {
+            contract,
+            client,
+            config,
+            chain_id: chain_id.as_u64(),
+        })
+    }
+
+    pub fn config(&self) -> &RegistryConfig {
+        &self.config
+    }
+
+    /// the chain id reported by the connected node
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn registry_address(&self) -> Address {
+        self.contract.address()
+    }
+
+    /// parse a DID, bare addresses and public keys are placed on the connected chain
+    pub fn did(&self, input: &str) -> Result<Did, DidEthError> {
+        Ok(Did::parse_on_chain(input, self.chain_id)?)
+    }
+
+    /// the registry identity of `did`, which must be on the connected chain
+    pub fn identity(&self, did: &Did) -> Result<Address, DidEthError> {
+        if did.chain_id() != self.chain_id {
+            return Err(DidEthError::ChainMismatch {
+                connected: self.chain_id.into(),
+                expected: did.chain_id(),
+            });
+        }
+        Ok(did.address())
+    }
+
+    /// the owner of `did`, the zero address once it is deactivated
+    pub async fn owner(&self, did: &Did) -> Result<String, DidEthError> {
+        let id_as_address = self.identity(did)?;
+        let mut owner = self.contract.identity_owner(id_as_address).call().await?;
+        // identityOwner reports a deactivated identity as owning itself
+        if owner == id_as_address && self.is_deactivated(did).await? {
+            owner = Address::zero();
+        }
+        let owner_as_string = format!("{owner:#032x}");
+        tracing::info!("Owner: {owner_as_string}");
+        Ok(owner_as_string)
+    }
+
+    /// whether ownership of `did` has been transferred to the zero address
+    pub async fn is_deactivated(&self, did: &Did) -> Result<bool, DidEthError> {
+        let id_as_address = self.identity(did)?;
+        // a transferred identity has an owner, only identities owning themselves need their history checked
+        let owner = self.contract.owners(id_as_address).call().await?;
+        if !owner.is_zero() {
+            return Ok(false);
+        }
+        let history = self.history(did).await?;
+        Ok(owner_at(&history, id_as_address).is_zero())
+    }
+
+    pub async fn valid_delegate(
+        &self,
+        did: &Did,
+        delegate_type: DelegateType,
+        delegate: String,
+    ) -> Result<bool, DidEthError> {
+        let id_as_address = self.identity(did)?;
+        let delegate_as_address = parse_address(&delegate)?;
+        let valid = self
+            .contract
+            .valid_delegate(
+                id_as_address,
+                delegate_type.to_bytes32(),
+                delegate_as_address,
+            )
+            .call()
+            .await?;
+        tracing::info!("{delegate_type} delegate {delegate} valid: {valid}");
+        Ok(valid)
+    }
+
+    /// the registry nonce for meta transactions on `did`, which is tracked per owner
+    pub async fn nonce(&self, did: &Did) -> Result<U256, DidEthError> {
+        let id_as_address = self.identity(did)?;
+        let owner = self.contract.identity_owner(id_as_address).call().await?;
+        let nonce = self.contract.nonce(owner).call().await?;
+        tracing::info!("Nonce: {nonce}");
+        Ok(nonce)
+    }
+
+    /// the block of the latest registry change for `did`, 0 if it never changed
+    pub async fn changed(&self, did: &Did) -> Result<u64, DidEthError> {
+        let id_as_address = self.identity(did)?;
+        let changed = self.contract.changed(id_as_address).call().await?;
+        Ok(changed.as_u64())
+    }
+
+    /// every registry event for `did`, oldest first
+    ///
+    /// Follows the `previousChange` chain back from `changed(id)`, collecting the
+    /// attribute, delegate and owner events of each block in the chain.  Logs are
+    /// requested `log_range` blocks at a time, so a chain of nearby changes costs
+    /// a single `eth_getLogs` call.
+    pub async fn history(&self, did: &Did) -> Result<Vec<HistoryEntry>, DidEthError> {
+        let id_as_address = self.identity(did)?;
+        let mut log_range = self.config.log_range.max(1);
+        // keyed by position so logs returned twice are only applied once
+        let mut events = BTreeMap::new();
+        let mut fetched_from = u64::MAX;
+        let mut history = Vec::new();
+        let mut prev_change = self.contract.changed(id_as_address).call().await?;
+        while !prev_change.is_zero() {
+            let block = prev_change.as_u64();
+            tracing::info!("prev_change: {block}");
+            if block < fetched_from {
+                let from = block.saturating_sub(log_range - 1);
+                let logs = self
+                    .logs_in_range(id_as_address, from, block, &mut log_range)
+                    .await?;
+                for log in logs {
+                    if log.removed == Some(true) {
+                        continue;
+                    }
+                    let entry = HistoryEntry::from_log(&log)?;
+                    events.insert((entry.block_number, entry.log_index), entry);
+                }
+                fetched_from = from;
+            }
+            let block_history = events
+                .range((block, 0)..=(block, u64::MAX))
+                .map(|(_, entry)| entry.clone())
+                .collect::<Vec<_>>();
+            // an empty block would silently drop every older change
+            if block_history.is_empty() {
+                tracing::warn!("no logs for {did} in block {block}");
+                return Err(DidEthError::MissingHistory(block));
+            }
+            // earlier events in the same block point back at this block
+            let next_change = block_history
+                .iter()
+                .map(HistoryEntry::previous_change)
+                .filter(|change| *change < prev_change)
+                .min()
+                .unwrap_or_default();
+            history.extend(block_history.into_iter().rev());
+            prev_change = next_change;
+        }
+        history.reverse();
+        Ok(history)
+    }
+
+    /// registry logs of `identity` in blocks `from..=to`
+    ///
+    /// Ranges the provider rejects for spanning too many blocks or results are
+    /// split in half and retried down to a single block, any other failure is
+    /// returned.  The narrowed `log_range` is kept for the rest of the walk.
+    async fn logs_in_range(
+        &self,
+        identity: Address,
+        from: u64,
+        to: u64,
+        log_range: &mut u64,
+    ) -> Result<Vec<Log>, DidEthError> {
+        let mut logs = Vec::new();
+        let mut ranges = vec![(from, to)];
+        while let Some((from, to)) = ranges.pop() {
+            let filter = Filter::new()
+                .from_block(from)
+                .to_block(to)
+                .address(self.contract.address())
+                .topic0(vec![
+                    DidattributeChangedFilter::signature(),
+                    DiddelegateChangedFilter::signature(),
+                    DidownerChangedFilter::signature(),
+                ])
+                .topic1(H256::from(identity));
+            tracing::debug!("filter: {:?}", filter);
+            match self.client.get_logs(&filter).await {
+                Ok(range_logs) => logs.extend(range_logs),
+                Err(err) if from < to && err.as_error_response().is_some_and(is_range_limit) => {
+                    tracing::warn!("splitting log range {from}..={to}: {err}");
+                    let middle = from + (to - from) / 2;
+                    *log_range = (*log_range).min(middle - from + 1);
+                    ranges.push((middle + 1, to));
+                    ranges.push((from, middle));
+                }
+                Err(err) => return Err(DidEthError::transport(err)),
+            }
+        }
+        Ok(logs)
+    }
+
+    /// the latest block timestamp, validity periods are compared against it
+    pub async fn block_timestamp(&self) -> Result<U256, DidEthError> {
+        let block = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(DidEthError::transport)?;
+        let block_timestamp = block.map(|block| block.timestamp).unwrap_or_default();
+        tracing::info!("block_timestamp: {block_timestamp}");
+        Ok(block_timestamp)
+    }
+
+    /// the timestamp of block `number`, which must exist
+    pub async fn block_timestamp_at(&self, number: u64) -> Result<U256, DidEthError> {
+        let block = self
+            .client
+            .get_block(number)
+            .await
+            .map_err(DidEthError::transport)?
+            .ok_or(DidEthError::BlockNotFound(number))?;
+        Ok(block.timestamp)
+    }
+
+    /// the number of events of `history` up to the requested version, with the
+    /// timestamp validity periods are compared against at that version
+    async fn version_of(
+        &self,
+        history: &[HistoryEntry],
+        options: &ResolutionOptions,
+    ) -> Result<(usize, U256), DidEthError> {
+        if let Some(version_id) = options.version_id {
+            let now = self.block_timestamp_at(version_id).await?;
+            let count = history
+                .iter()
+                .take_while(|entry| entry.block_number <= version_id)
+                .count();
+            return Ok((count, now));
+        }
+        if let Some(version_time) = options.version_time {
+            let now = U256::from(version_time.timestamp().max(0));
+            let mut blocks = history
+                .iter()
+                .map(|entry| entry.block_number)
+                .collect::<Vec<_>>();
+            blocks.dedup();
+            // block timestamps increase with the block number, binary search for the
+            // first block after `now`
+            let (mut low, mut high) = (0, blocks.len());
+            while low < high {
+                let middle = low + (high - low) / 2;
+                if self.block_timestamp_at(blocks[middle]).await? <= now {
+                    low = middle + 1;
+                } else {
+                    high = middle;
+                }
+            }
+            let count = match low {
+                0 => 0,
+                low => history
+                    .iter()
+                    .take_while(|entry| entry.block_number <= blocks[low - 1])
+                    .count(),
+            };
+            return Ok((count, now));
+        }
+        Ok((history.len(), self.block_timestamp().await?))
+    }
+
+    /// document metadata for the first `count` events of `history`
+    async fn document_metadata(
+        &self,
+        history: &[HistoryEntry],
+        count: usize,
+    ) -> Result<DidDocumentMetadata, DidEthError> {
+        let created = history.first().filter(|_| count > 0);
+        let updated = history[..count].last();
+        let next = history.get(count);
+        let blocks = [created, updated, next]
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.block_number)
+            .collect::<BTreeSet<u64>>();
+        let mut timestamps = HashMap::new();
+        for block in blocks {
+            timestamps.insert(block, self.block_timestamp_at(block).await?);
+        }
+        let timestamp = |entry: &HistoryEntry| iso_timestamp(timestamps[&entry.block_number]);
+        Ok(DidDocumentMetadata {
+            created: created.map(timestamp),
+            updated: updated.map(timestamp),
+            version_id: updated.map(|entry| entry.block_number.to_string()),
+            next_version_id: next.map(|entry| entry.block_number.to_string()),
+            next_update: next.map(timestamp),
+            deactivated: None,
+        })
+    }
+
+    /// attributes of `did` that are valid at the latest block
+    pub async fn attribute(&self, did: &Did) -> Result<Vec<(String, String)>, DidEthError> {
+        let history = self.history(did).await?;
+        let block_timestamp = self.block_timestamp().await?;
+        Ok(active_attributes(&history, block_timestamp))
+    }
+
+    /// the DID document of `did` as of the latest block
+    pub async fn document(&self, did: &Did) -> Result<DidDocument, DidEthError> {
+        self.document_at(did, &ResolutionOptions::latest()).await
+    }
+
+    /// the DID document of `did` as it was at the version selected by `options`
+    ///
+    /// Only events up to that version are applied, including owner changes, and
+    /// validity periods are compared against its timestamp, so keys valid then are
+    /// present even if they have since been revoked or expired.  The document `id`
+    /// is the display form of `did`, `resolve` keeps the DID as it was given.
+    pub async fn document_at(
+        &self,
+        did: &Did,
+        options: &ResolutionOptions,
+    ) -> Result<DidDocument, DidEthError> {
+        let versioned = self.versioned(did, options).await?;
+        Ok(DidDocument::from_history(
+            &did.to_string(),
+            did,
+            versioned.owner,
+            versioned.events(),
+            versioned.now,
+        ))
+    }
+
+    /// the history of `did` with the version selected by `options` and its owner
+    async fn versioned(
+        &self,
+        did: &Did,
+        options: &ResolutionOptions,
+    ) -> Result<Versioned, DidEthError> {
+        let identity = self.identity(did)?;
+        let history = self.history(did).await?;
+        let (count, now) = self.version_of(&history, options).await?;
+        let owner = owner_at(&history[..count], identity);
+        Ok(Versioned {
+            history,
+            count,
+            owner,
+            now,
+        })
+    }
+
+    /// resolve `did` to its document and metadata, failures are reported in the
+    /// resolution metadata
+    pub async fn resolve(&self, did: &str, options: &ResolutionOptions) -> ResolutionResult {
+        match self.resolve_did(did, options).await {
+            Ok(resolved) => resolved.result,
+            Err(err) => {
+                tracing::warn!("failed to resolve {did}: {err}");
+                ResolutionResult::error(&err)
+            }
+        }
+    }
+
+    pub(crate) async fn resolve_did(
+        &self,
+        did: &str,
+        options: &ResolutionOptions,
+    ) -> Result<Resolved, DidEthError> {
+        let id = did;
+        let did = did.parse::<Did>()?;
+        let versioned = self.versioned(&did, options).await?;
+        let document =
+            DidDocument::from_history(id, &did, versioned.owner, versioned.events(), versioned.now);
+        let mut metadata = self
+            .document_metadata(&versioned.history, versioned.count)
+            .await?;
+        if versioned.owner.is_zero() {
+            metadata.deactivated = Some(true);
+        }
+        Ok(Resolved {
+            result: ResolutionResult::resolved(document, metadata),
+            timestamp: versioned.now,
+            expires: next_expiry(versioned.events(), versioned.now),
+        })
+    }
+
+    /// delegates of `did` that are valid at the latest block
+    pub async fn delegates(&self, did: &Did) -> Result<Vec<(DelegateType, Address)>, DidEthError> {
+        let history = self.history(did).await?;
+        let block_timestamp = self.block_timestamp().await?;
+        Ok(active_delegates(&history, block_timestamp))
+    }
+}
+
+/// JSON-RPC code for a request over a provider limit (EIP-1474)
+const LIMIT_EXCEEDED: i64 = -32005;
+/// JSON-RPC code for invalid params, which some providers return for a range too wide
+const INVALID_PARAMS: i64 = -32602;
+
+/// whether a rejected `eth_getLogs` asked for too many blocks or results
+///
+/// The error code decides: limit exceeded, unless the limit is the request rate, and
+/// invalid params are range limits.  Providers that answer a range limit with a
+/// generic server error are only recognized by the wording of the message.
+fn is_range_limit(err: &JsonRpcError) -> bool {
+    const LIMITS: [&str; 6] = [
+        "range",
+        "limit",
+        "too many",
+        "too large",
+        "exceed",
+        "response size",
+    ];
+    let message = err.message.to_lowercase();
+    let rate_limited = message.contains("rate");
+    match err.code {
+        LIMIT_EXCEEDED => !rate_limited,
+        INVALID_PARAMS => true,
+        _ => !rate_limited && LIMITS.iter().any(|limit| message.contains(limit)),
+    }
+}
+
+/// Apply attribute changes oldest first, matching the reference did-ethr-resolver
+///
+/// Each (name, value) pair is keyed independently.  A change valid at `now` adds the
+/// attribute, a revocation (`validTo == 0`) or an expired change removes it, and a
+/// later change re-adds it.
+pub fn active_attributes(history: &[HistoryEntry], now: U256) -> Vec<(String, String)> {
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    for entry in history.iter() {
+        if let DIDRegistryEvents::DidattributeChangedFilter(change) = &entry.event {
+            let attribute_name = bytes32_to_string(&change.name);
+            let attribute_value = String::from_utf8_lossy(&change.value).to_string();
+            tracing::debug!(
+                "attribute {attribute_name}={attribute_value} valid until: {}",
+                change.valid_to
+            );
+            let key = (attribute_name, attribute_value);
+            if is_valid(change.valid_to, now) {
+                if !attributes.contains(&key) {
+                    attributes.push(key);
+                }
+            } else {
+                tracing::debug!("revoked or expired: {}", key.0);
+                attributes.retain(|attribute| *attribute != key);
+            }
+        }
+    }
+    attributes
+}
+
+/// The owner of `identity` after the owner changes in `history`, an identity owns itself until transferred
+pub fn owner_at(history: &[HistoryEntry], identity: Address) -> Address {
+    history
+        .iter()
+        .filter_map(|entry| match &entry.event {
+            DIDRegistryEvents::DidownerChangedFilter(change) => Some(change.owner),
+            _ => None,
+        })
+        .last()
+        .unwrap_or(identity)
+}
+
+/// Apply delegate changes oldest first, the latest change for a delegate decides its validity
+pub fn active_delegates(history: &[HistoryEntry], now: U256) -> Vec<(DelegateType, Address)> {
+    let mut delegates: Vec<(DelegateType, Address)> = Vec::new();
+    for entry in history.iter() {
+        if let DIDRegistryEvents::DiddelegateChangedFilter(change) = &entry.event {
+            let Ok(delegate_type) = DelegateType::from_bytes32(&change.delegate_type) else {
+                tracing::warn!("unknown delegate type for {:#x}", change.delegate);
+                continue;
+            };
+            let key = (delegate_type, change.delegate);
+            if is_valid(change.valid_to, now) {
+                if !delegates.contains(&key) {
+                    delegates.push(key);
+                }
+            } else {
+                delegates.retain(|delegate| *delegate != key);
+            }
+        }
+    }
+    delegates
+}
+
+/// the earliest `validTo` of the attribute and delegate changes in `history` valid at `now`,
+/// after which the active attributes or delegates may differ
+pub(crate) fn next_expiry(history: &[HistoryEntry], now: U256) -> Option<U256> {
+    history
+        .iter()
+        .filter_map(|entry| match &entry.event {
+            DIDRegistryEvents::DidattributeChangedFilter(change) => Some(change.valid_to),
+            DIDRegistryEvents::DiddelegateChangedFilter(change) => Some(change.valid_to),
+            DIDRegistryEvents::DidownerChangedFilter(_) => None,
+        })
+        .filter(|valid_to| is_valid(*valid_to, now))
+        .min()
+}
+
+pub(crate) fn is_valid(valid_to: U256, now: U256) -> bool {
+    !valid_to.is_zero() && valid_to >= now
+}
+
+/// bytes32 strings are right padded with zeros
+pub(crate) fn bytes32_to_string(fixed: &[u8; 32]) -> String {
+    String::from_utf8_lossy(fixed)
+        .chars()
+        .take_while(|c| !char::is_control(*c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DidattributeChangedFilter, DiddelegateChangedFilter, DidownerChangedFilter, Network,
+        ResolutionError, SEPOLIA_CHAIN_ID,
+    };
+    use ethers::{
+        abi::{encode, AbiEncode, Token},
+        contract::EthEvent,
+        providers::{JsonRpcError, MockProvider, MockResponse},
+        types::{Block, Bytes, U64},
+        utils::format_bytes32_string,
+    };
+
+    fn identity() -> Address {
+        Address::repeat_byte(0x11)
+    }
+
+    fn log(signature: H256, data: Vec<Token>, block: u64, log_index: u64) -> Log {
+        Log {
+            address: RegistryConfig::for_network(Network::Sepolia).registry,
+            topics: vec![signature, H256::from(identity())],
+            data: encode(&data).into(),
+            block_number: Some(block.into()),
+            log_index: Some(log_index.into()),
+            ..Default::default()
+        }
+    }
+
+    fn attribute_log(name: &str, value: &str, valid_to: u64, prev: u64, block: u64) -> Log {
+        let data = vec![
+            Token::FixedBytes(format_bytes32_string(name).unwrap().to_vec()),
+            Token::Bytes(value.as_bytes().to_vec()),
+            Token::Uint(valid_to.into()),
+            Token::Uint(prev.into()),
+        ];
+        log(DidattributeChangedFilter::signature(), data, block, 0)
+    }
+
+    fn delegate_log(delegate: Address, valid_to: u64, prev: u64, block: u64) -> Log {
+        let data = vec![
+            Token::FixedBytes(DelegateType::SigAuth.to_bytes32().to_vec()),
+            Token::Address(delegate),
+            Token::Uint(valid_to.into()),
+            Token::Uint(prev.into()),
+        ];
+        log(DiddelegateChangedFilter::signature(), data, block, 1)
+    }
+
+    fn owner_log(owner: Address, prev: u64, block: u64) -> Log {
+        let data = vec![Token::Address(owner), Token::Uint(prev.into())];
+        log(DidownerChangedFilter::signature(), data, block, 0)
+    }
+
+    async fn mocked_resolver() -> (Resolver<Provider<MockProvider>>, MockProvider) {
+        mocked_resolver_with(RegistryConfig::for_network(Network::Sepolia)).await
+    }
+
+    async fn mocked_resolver_with(
+        config: RegistryConfig,
+    ) -> (Resolver<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(SEPOLIA_CHAIN_ID)).unwrap();
+        let resolver = Resolver::from_client(Arc::new(provider), config)
+            .await
+            .unwrap();
+        (resolver, mock)
+    }
+
+    #[tokio::test]
+    async fn test_history_follows_all_events() {
+        let (resolver, mock) = mocked_resolver().await;
+        let delegate = Address::repeat_byte(0x22);
+        // mocked responses are served last in, first out
+        mock.push::<Vec<Log>, Vec<Log>>(vec![
+            owner_log(Address::repeat_byte(0x33), 0, 3),
+            attribute_log("did/svc/Messaging", "https://example.com", 100, 3, 7),
+            delegate_log(delegate, 100, 7, 7),
+        ])
+        .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+
+        let history = resolver
+            .history(&Did::new(SEPOLIA_CHAIN_ID, identity()))
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].block_number, 3);
+        assert!(matches!(
+            history[0].event,
+            DIDRegistryEvents::DidownerChangedFilter(_)
+        ));
+        assert_eq!(history[1].block_number, 7);
+        assert!(matches!(
+            history[1].event,
+            DIDRegistryEvents::DidattributeChangedFilter(_)
+        ));
+        assert_eq!(history[2].log_index, 1);
+        assert!(matches!(
+            history[2].event,
+            DIDRegistryEvents::DiddelegateChangedFilter(_)
+        ));
+    }
+
+    fn push_block(mock: &MockProvider, number: u64, timestamp: u64) {
+        let block = Block::<H256> {
+            number: Some(number.into()),
+            timestamp: timestamp.into(),
+            ..Default::default()
+        };
+        mock.push::<Block<H256>, Block<H256>>(block).unwrap();
+    }
+
+    // owner changed to 0x33 in block 3 at t=50, sigAuth delegate valid to t=150 added in block 7 at t=120
+    fn push_versioned_history(mock: &MockProvider) {
+        mock.push::<Vec<Log>, Vec<Log>>(vec![
+            owner_log(Address::repeat_byte(0x33), 0, 3),
+            delegate_log(Address::repeat_byte(0x22), 150, 3, 7),
+        ])
+        .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+    }
+
+    fn controller_and_delegates(document: &DidDocument) -> (Option<String>, usize) {
+        (
+            document.verification_method[0]
+                .blockchain_account_id
+                .clone(),
+            document.verification_method.len() - 1,
+        )
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_connect_ipc() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::UnixListener,
+        };
+
+        let path = std::env::temp_dir().join(format!("dideth-{}.ipc", std::process::id()));
+        let listener = UnixListener::bind(&path).unwrap();
+        // answer the chain id request like a node's IPC socket
+        let node = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 1024];
+            let read = stream.read(&mut buffer).await.unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&buffer[..read]).unwrap();
+            assert_eq!(request["method"], "eth_chainId");
+            let response = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": U64::from(SEPOLIA_CHAIN_ID),
+            });
+            stream
+                .write_all(response.to_string().as_bytes())
+                .await
+                .unwrap();
+        });
+
+        let config = RegistryConfig::for_network(Network::Sepolia);
+        let resolver = Resolver::connect_ipc(&path, config).await.unwrap();
+        assert_eq!(resolver.chain_id(), SEPOLIA_CHAIN_ID);
+        node.await.unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_document_at_version_id() {
+        let did = Did::new(SEPOLIA_CHAIN_ID, identity());
+        let owner = format!(
+            "eip155:{SEPOLIA_CHAIN_ID}:{}",
+            ethers::utils::to_checksum(&Address::repeat_byte(0x33), None)
+        );
+        let cases = [(3, 50, 0), (7, 120, 1)];
+        for (version_id, timestamp, delegates) in cases {
+            let (resolver, mock) = mocked_resolver().await;
+            push_block(&mock, version_id, timestamp);
+            push_versioned_history(&mock);
+            let options = ResolutionOptions::latest().with_version_id(version_id);
+            let document = resolver.document_at(&did, &options).await.unwrap();
+            assert_eq!(
+                controller_and_delegates(&document),
+                (Some(owner.clone()), delegates),
+                "versionId {version_id}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_document_at_version_time() {
+        let did = Did::new(SEPOLIA_CHAIN_ID, identity());
+        // (versionTime, timestamps of the blocks searched, methods)
+        let cases = [
+            ("1970-01-01T00:02:20Z", vec![(7, 120)], 2),
+            ("1970-01-01T00:01:40Z", vec![(7, 120), (3, 50)], 1),
+            ("1970-01-01T00:00:40Z", vec![(7, 120), (3, 50)], 1),
+        ];
+        for (version_time, searched, methods) in cases {
+            let (resolver, mock) = mocked_resolver().await;
+            for (number, timestamp) in searched.into_iter().rev() {
+                push_block(&mock, number, timestamp);
+            }
+            push_versioned_history(&mock);
+            let version_time = ResolutionOptions::parse_version_time(version_time).unwrap();
+            let options = ResolutionOptions::latest().with_version_time(version_time);
+            let document = resolver.document_at(&did, &options).await.unwrap();
+            assert_eq!(
+                document.verification_method.len(),
+                methods,
+                "{version_time}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_document_latest_expired() {
+        let did = Did::new(SEPOLIA_CHAIN_ID, identity());
+        let (resolver, mock) = mocked_resolver().await;
+        push_block(&mock, 9, 200);
+        push_versioned_history(&mock);
+        let document = resolver.document(&did).await.unwrap();
+        assert_eq!(document.verification_method.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_metadata() {
+        let did = Did::new(SEPOLIA_CHAIN_ID, identity());
+        let (resolver, mock) = mocked_resolver().await;
+        push_block(&mock, 7, 120);
+        push_block(&mock, 3, 50);
+        push_block(&mock, 3, 50);
+        push_versioned_history(&mock);
+        let options = ResolutionOptions::latest().with_version_id(3);
+        let result = resolver.resolve(&did.to_string(), &options).await;
+        assert_eq!(
+            result.did_resolution_metadata.content_type.as_deref(),
+            Some(crate::DID_LD_JSON)
+        );
+        assert_eq!(result.did_resolution_metadata.error, None);
+        assert_eq!(result.did_document.unwrap().id, did.to_string());
+        assert_eq!(
+            result.did_document_metadata,
+            DidDocumentMetadata {
+                created: Some("1970-01-01T00:00:50Z".to_string()),
+                updated: Some("1970-01-01T00:00:50Z".to_string()),
+                version_id: Some("3".to_string()),
+                next_version_id: Some("7".to_string()),
+                next_update: Some("1970-01-01T00:02:00Z".to_string()),
+                deactivated: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_errors() {
+        let (resolver, _mock) = mocked_resolver().await;
+        let latest = ResolutionOptions::latest();
+        let cases = [
+            ("did:ethr:0x1234".to_string(), ResolutionError::InvalidDid),
+            (
+                format!("did:web:{:#x}", identity()),
+                ResolutionError::InvalidDid,
+            ),
+            (
+                format!("did:ethr:goerli:{:#x}", identity()),
+                ResolutionError::UnsupportedNetwork,
+            ),
+            (
+                format!("did:ethr:{:#x}", identity()),
+                ResolutionError::UnsupportedNetwork,
+            ),
+        ];
+        for (did, expected) in cases {
+            let result = resolver.resolve(&did, &latest).await;
+            assert_eq!(
+                result.did_resolution_metadata.error,
+                Some(expected),
+                "{did}"
+            );
+            assert!(result.did_document.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_deactivated() {
+        let did = Did::new(SEPOLIA_CHAIN_ID, identity());
+        let (resolver, mock) = mocked_resolver().await;
+        push_block(&mock, 7, 120);
+        push_block(&mock, 3, 50);
+        push_block(&mock, 9, 130);
+        mock.push::<Vec<Log>, Vec<Log>>(vec![
+            owner_log(Address::zero(), 0, 3),
+            delegate_log(Address::repeat_byte(0x22), 150, 3, 7),
+        ])
+        .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+        let result = resolver
+            .resolve(&did.to_string(), &ResolutionOptions::latest())
+            .await;
+        assert_eq!(result.did_document_metadata.deactivated, Some(true));
+        assert_eq!(
+            result.did_document_metadata.version_id.as_deref(),
+            Some("7")
+        );
+        assert_eq!(
+            result.did_document,
+            Some(DidDocument::deactivated(&did.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_deactivated() {
+        let did = Did::new(SEPOLIA_CHAIN_ID, identity());
+        let (resolver, mock) = mocked_resolver().await;
+        mock.push::<Bytes, Bytes>(Address::repeat_byte(0x33).encode().into())
+            .unwrap();
+        assert!(!resolver.is_deactivated(&did).await.unwrap());
+
+        mock.push::<Vec<Log>, Vec<Log>>(vec![owner_log(Address::zero(), 0, 3)])
+            .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(3).encode().into())
+            .unwrap();
+        mock.push::<Bytes, Bytes>(Address::zero().encode().into())
+            .unwrap();
+        assert!(resolver.is_deactivated(&did).await.unwrap());
+
+        // an identity that never changed owner owns itself
+        mock.push::<Bytes, Bytes>(U256::zero().encode().into())
+            .unwrap();
+        mock.push::<Bytes, Bytes>(Address::zero().encode().into())
+            .unwrap();
+        assert!(!resolver.is_deactivated(&did).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_history_same_block_transactions() {
+        let (resolver, mock) = mocked_resolver().await;
+        let delegate = Address::repeat_byte(0x22);
+        let owner = Address::repeat_byte(0x33);
+        let mut owner_change = owner_log(owner, 7, 7);
+        owner_change.log_index = Some(2.into());
+        // logs are not guaranteed to be returned in order
+        mock.push::<Vec<Log>, Vec<Log>>(vec![
+            owner_change,
+            delegate_log(delegate, 100, 7, 7),
+            attribute_log("did/svc/B", "b", 100, 3, 7),
+            attribute_log("did/svc/A", "a", 100, 0, 3),
+        ])
+        .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+
+        let history = resolver
+            .history(&Did::new(SEPOLIA_CHAIN_ID, identity()))
+            .await
+            .unwrap();
+        let events = history
+            .iter()
+            .map(|entry| (entry.block_number, entry.registry_event()))
+            .collect::<Vec<_>>();
+        let attribute = |name: &str, value: &str, previous_change: u64| {
+            RegistryEvent::AttributeChanged(crate::AttributeChanged {
+                identity: identity(),
+                name: name.to_string(),
+                value: Bytes::from(value.as_bytes().to_vec()),
+                valid_to: 100.into(),
+                previous_change: previous_change.into(),
+            })
+        };
+        assert_eq!(
+            events,
+            vec![
+                (3, attribute("did/svc/A", "a", 0)),
+                (7, attribute("did/svc/B", "b", 3)),
+                (
+                    7,
+                    RegistryEvent::DelegateChanged(crate::DelegateChanged {
+                        identity: identity(),
+                        delegate_type: "sigAuth".to_string(),
+                        delegate,
+                        valid_to: 100.into(),
+                        previous_change: 7.into(),
+                    })
+                ),
+                (
+                    7,
+                    RegistryEvent::OwnerChanged(crate::OwnerChanged {
+                        identity: identity(),
+                        owner,
+                        previous_change: 7.into(),
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_log_ranges() {
+        let config = RegistryConfig::for_network(Network::Sepolia).with_log_range(4);
+        let (resolver, mock) = mocked_resolver_with(config).await;
+        // blocks 4..=7 first, then 0..=3 once the chain leaves that range
+        mock.push::<Vec<Log>, Vec<Log>>(vec![owner_log(Address::repeat_byte(0x33), 0, 3)])
+            .unwrap();
+        let attribute = attribute_log("did/svc/A", "a", 100, 3, 7);
+        mock.push::<Vec<Log>, Vec<Log>>(vec![attribute.clone(), attribute])
+            .unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+
+        let history = resolver
+            .history(&Did::new(SEPOLIA_CHAIN_ID, identity()))
+            .await
+            .unwrap();
+        let blocks = history
+            .iter()
+            .map(|entry| entry.block_number)
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![3, 7]);
+    }
+
+    #[tokio::test]
+    async fn test_history_splits_rejected_ranges() {
+        let (resolver, mock) = mocked_resolver().await;
+        // 0..=7 is rejected, then 0..=3 and 4..=7 are requested
+        mock.push::<Vec<Log>, Vec<Log>>(vec![delegate_log(Address::repeat_byte(0x22), 100, 3, 7)])
+            .unwrap();
+        mock.push::<Vec<Log>, Vec<Log>>(vec![owner_log(Address::repeat_byte(0x33), 0, 3)])
+            .unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32005,
+            message: "query returned more than 10000 results".to_string(),
+            data: None,
+        }));
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+
+        let history = resolver
+            .history(&Did::new(SEPOLIA_CHAIN_ID, identity()))
+            .await
+            .unwrap();
+        let blocks = history
+            .iter()
+            .map(|entry| entry.block_number)
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![3, 7]);
+    }
+
+    #[tokio::test]
+    async fn test_history_returns_other_errors() {
+        let (resolver, mock) = mocked_resolver().await;
+        // any response left over would be used by a split range
+        mock.push::<Vec<Log>, Vec<Log>>(vec![owner_log(Address::repeat_byte(0x33), 0, 3)])
+            .unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32005,
+            message: "project ID request rate exceeded".to_string(),
+            data: None,
+        }));
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+        assert!(matches!(
+            resolver
+                .history(&Did::new(SEPOLIA_CHAIN_ID, identity()))
+                .await,
+            Err(DidEthError::Transport(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_range_limit() {
+        let error = |code: i64, message: &str| JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        };
+        assert!(is_range_limit(&error(
+            -32005,
+            "query returned more than 10000 results"
+        )));
+        assert!(is_range_limit(&error(
+            -32602,
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"
+        )));
+        // the code decides whatever the wording
+        assert!(is_range_limit(&error(-32005, "query timeout")));
+        assert!(is_range_limit(&error(-32602, "invalid params")));
+        assert!(!is_range_limit(&error(
+            -32005,
+            "daily request count exceeded, request rate limited"
+        )));
+        // other codes fall back to the message
+        assert!(is_range_limit(&error(
+            -32000,
+            "exceed maximum block range: 5000"
+        )));
+        assert!(!is_range_limit(&error(-32000, "request rate exceeded")));
+        assert!(!is_range_limit(&error(-32000, "header not found")));
+        assert!(!is_range_limit(&error(-32601, "method not found")));
+    }
+
+    #[tokio::test]
+    async fn test_history_missing_block() {
+        let (resolver, mock) = mocked_resolver().await;
+        mock.push::<Vec<Log>, Vec<Log>>(vec![]).unwrap();
+        mock.push::<Bytes, Bytes>(U256::from(7).encode().into())
+            .unwrap();
+        assert!(matches!(
+            resolver
+                .history(&Did::new(SEPOLIA_CHAIN_ID, identity()))
+                .await,
+            Err(DidEthError::MissingHistory(7))
+        ));
+    }
+
+    #[test]
+    fn test_owner_at() {
+        let owner = |owner: Address| HistoryEntry {
+            block_number: 0,
+            log_index: 0,
+            event: DidownerChangedFilter {
+                identity: identity(),
+                owner,
+                previous_change: U256::zero(),
+            }
+            .into(),
+        };
+        assert_eq!(owner_at(&[], identity()), identity());
+        let history = vec![
+            owner(Address::repeat_byte(0x33)),
+            owner(Address::repeat_byte(0x44)),
+        ];
+        assert_eq!(owner_at(&history, identity()), Address::repeat_byte(0x44));
+        assert_eq!(
+            owner_at(&history[..1], identity()),
+            Address::repeat_byte(0x33)
+        );
+    }
+
+    #[test]
+    fn test_bytes32_to_string() {
+        let name = format_bytes32_string("did/pub/Secp256k1/veriKey/hex").unwrap();
+        assert_eq!(bytes32_to_string(&name), "did/pub/Secp256k1/veriKey/hex");
+        assert_eq!(bytes32_to_string(&[0u8; 32]), "");
+    }
+
+    const NOW: u64 = 1_000;
+
+    fn set(value: &str, valid_to: u64) -> HistoryEntry {
+        change("did/svc/MessagingService", value, valid_to)
+    }
+
+    fn revoke(value: &str) -> HistoryEntry {
+        change("did/svc/MessagingService", value, 0)
+    }
+
+    fn change(name: &str, value: &str, valid_to: u64) -> HistoryEntry {
+        HistoryEntry {
+            block_number: 0,
+            log_index: 0,
+            event: DidattributeChangedFilter {
+                identity: identity(),
+                name: format_bytes32_string(name).unwrap(),
+                value: Bytes::from(value.as_bytes().to_vec()),
+                valid_to: valid_to.into(),
+                previous_change: U256::zero(),
+            }
+            .into(),
+        }
+    }
+
+    fn values(history: Vec<HistoryEntry>) -> Vec<String> {
+        active_attributes(&history, NOW.into())
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    #[test]
+    fn test_attribute_revocation_matrix() {
+        let cases: Vec<(&str, Vec<HistoryEntry>, Vec<&str>)> = vec![
+            ("set", vec![set("a", 2_000)], vec!["a"]),
+            ("set revoke", vec![set("a", 2_000), revoke("a")], vec![]),
+            ("revoke set", vec![revoke("a"), set("a", 2_000)], vec!["a"]),
+            (
+                "set revoke set",
+                vec![set("a", 2_000), revoke("a"), set("a", 2_000)],
+                vec!["a"],
+            ),
+            (
+                "set revoke set revoke",
+                vec![set("a", 2_000), revoke("a"), set("a", 2_000), revoke("a")],
+                vec![],
+            ),
+            ("revoke only", vec![revoke("a")], vec![]),
+            ("expired", vec![set("a", 999)], vec![]),
+            ("expires now", vec![set("a", NOW)], vec!["a"]),
+            (
+                "renewed after expiry",
+                vec![set("a", 500), set("a", 2_000)],
+                vec!["a"],
+            ),
+            (
+                "expired renewal",
+                vec![set("a", 2_000), set("a", 500)],
+                vec![],
+            ),
+            (
+                "set twice",
+                vec![set("a", 2_000), set("a", 3_000)],
+                vec!["a"],
+            ),
+            (
+                "distinct values",
+                vec![set("a", 2_000), set("b", 2_000)],
+                vec!["a", "b"],
+            ),
+            (
+                "revoke one value",
+                vec![set("a", 2_000), set("b", 2_000), revoke("a")],
+                vec!["b"],
+            ),
+            (
+                "revoke other value",
+                vec![set("a", 2_000), revoke("b")],
+                vec!["a"],
+            ),
+            (
+                "re-added value moves last",
+                vec![
+                    set("a", 2_000),
+                    set("b", 2_000),
+                    revoke("a"),
+                    set("a", 2_000),
+                ],
+                vec!["b", "a"],
+            ),
+        ];
+        for (case, history, expected) in cases {
+            assert_eq!(values(history), expected, "{case}");
+        }
+    }
+
+    #[test]
+    fn test_attribute_names_are_independent() {
+        let history = vec![
+            change("did/svc/MessagingService", "a", 2_000),
+            change("did/svc/OtherService", "a", 2_000),
+            revoke("a"),
+        ];
+        let attributes = active_attributes(&history, NOW.into());
+        assert_eq!(
+            attributes,
+            vec![("did/svc/OtherService".to_string(), "a".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_delegate_validity() {
+        let delegate = Address::repeat_byte(0x22);
+        let entry = |valid_to: u64| HistoryEntry {
+            block_number: 0,
+            log_index: 0,
+            event: DiddelegateChangedFilter {
+                identity: identity(),
+                delegate_type: DelegateType::VeriKey.to_bytes32(),
+                delegate,
+                valid_to: valid_to.into(),
+                previous_change: U256::zero(),
+            }
+            .into(),
+        };
+        let active = |history: Vec<HistoryEntry>| active_delegates(&history, NOW.into());
+        assert_eq!(
+            active(vec![entry(2_000)]),
+            vec![(DelegateType::VeriKey, delegate)]
+        );
+        // revokeDelegate sets validTo to the block timestamp of the revocation
+        assert!(active(vec![entry(2_000), entry(900)]).is_empty());
+        assert_eq!(
+            active(vec![entry(900), entry(2_000)]),
+            vec![(DelegateType::VeriKey, delegate)]
+        );
+        assert!(values(vec![entry(2_000)]).is_empty());
+    }
+}
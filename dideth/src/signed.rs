@@ -0,0 +1,389 @@
+use ethers::{
+    prelude::LocalWallet,
+    types::{Address, Bytes, RecoveryMessage, Signature, H256, U256},
+    utils::keccak256,
+};
+
+use crate::{error::name_to_bytes32, DelegateType, DidEthError};
+
+/// A registry operation that can be authorized by the identity owner's signature
+/// and submitted by any account through the `*Signed` registry functions
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Operation {
+    SetAttribute {
+        name: String,
+        value: Bytes,
+        validity: U256,
+    },
+    RevokeAttribute {
+        name: String,
+        value: Bytes,
+    },
+    AddDelegate {
+        delegate_type: DelegateType,
+        delegate: Address,
+        validity: U256,
+    },
+    RevokeDelegate {
+        delegate_type: DelegateType,
+        delegate: Address,
+    },
+    ChangeOwner {
+        new_owner: Address,
+    },
+    /// transfer ownership to the zero address, permanently deactivating the identity
+    Deactivate,
+}
+
+impl Operation {
+    /// the function name the registry mixes into the signed payload
+    pub fn function_name(&self) -> &'static str {
+        match self {
+            Operation::SetAttribute { .. } => "setAttribute",
+            Operation::RevokeAttribute { .. } => "revokeAttribute",
+            Operation::AddDelegate { .. } => "addDelegate",
+            Operation::RevokeDelegate { .. } => "revokeDelegate",
+            Operation::ChangeOwner { .. } | Operation::Deactivate => "changeOwner",
+        }
+    }
+
+    /// the owner a `changeOwner` operation transfers the identity to
+    pub fn new_owner(&self) -> Option<Address> {
+        match self {
+            Operation::ChangeOwner { new_owner } => Some(*new_owner),
+            Operation::Deactivate => Some(Address::zero()),
+            _ => None,
+        }
+    }
+
+    /// a zero address owner deactivates the identity, which must be asked for with `Deactivate`
+    pub fn validate(&self, identity: Address) -> Result<(), DidEthError> {
+        match self {
+            Operation::ChangeOwner { new_owner } if new_owner.is_zero() => {
+                Err(DidEthError::ZeroAddressOwner(identity))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// ERC-1056 signing payload
+    /// 0x19 || 0x00 || registry || nonce || identity || function name || operation data
+    pub fn payload(
+        &self,
+        registry: Address,
+        nonce: U256,
+        identity: Address,
+    ) -> Result<Vec<u8>, DidEthError> {
+        let mut payload = vec![0x19, 0x00];
+        payload.extend_from_slice(registry.as_bytes());
+        let mut nonce_bytes = [0u8; 32];
+        nonce.to_big_endian(&mut nonce_bytes);
+        payload.extend_from_slice(&nonce_bytes);
+        payload.extend_from_slice(identity.as_bytes());
+        payload.extend_from_slice(self.function_name().as_bytes());
+        match self {
+            Operation::SetAttribute {
+                name,
+                value,
+                validity,
+            } => {
+                payload.extend_from_slice(&name_to_bytes32(name)?);
+                payload.extend_from_slice(value);
+                payload.extend_from_slice(&u256_bytes(*validity));
+            }
+            Operation::RevokeAttribute { name, value } => {
+                payload.extend_from_slice(&name_to_bytes32(name)?);
+                payload.extend_from_slice(value);
+            }
+            Operation::AddDelegate {
+                delegate_type,
+                delegate,
+                validity,
+            } => {
+                payload.extend_from_slice(&delegate_type.to_bytes32());
+                payload.extend_from_slice(delegate.as_bytes());
+                payload.extend_from_slice(&u256_bytes(*validity));
+            }
+            Operation::RevokeDelegate {
+                delegate_type,
+                delegate,
+            } => {
+                payload.extend_from_slice(&delegate_type.to_bytes32());
+                payload.extend_from_slice(delegate.as_bytes());
+            }
+            Operation::ChangeOwner { new_owner } => {
+                payload.extend_from_slice(new_owner.as_bytes());
+            }
+            Operation::Deactivate => {
+                payload.extend_from_slice(Address::zero().as_bytes());
+            }
+        }
+        Ok(payload)
+    }
+
+    pub fn hash(
+        &self,
+        registry: Address,
+        nonce: U256,
+        identity: Address,
+    ) -> Result<H256, DidEthError> {
+        let payload = self.payload(registry, nonce, identity)?;
+        Ok(H256::from(keccak256(payload)))
+    }
+}
+
+fn u256_bytes(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+/// An operation signed by the identity owner, ready to be relayed by a funded wallet
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedMetaTransaction {
+    pub registry: Address,
+    pub identity: Address,
+    pub nonce: U256,
+    pub operation: Operation,
+    pub v: u8,
+    pub r: H256,
+    pub s: H256,
+}
+
+impl SignedMetaTransaction {
+    /// sign the operation with the owner key, this requires no network access
+    /// the nonce is the registry `nonce` of the current identity owner
+    pub fn sign(
+        wallet: &LocalWallet,
+        registry: Address,
+        identity: Address,
+        nonce: U256,
+        operation: Operation,
+    ) -> Result<Self, DidEthError> {
+        operation.validate(identity)?;
+        let hash = operation.hash(registry, nonce, identity)?;
+        let signature = wallet.sign_hash(hash)?;
+        let v = u8::try_from(signature.v)
+            .map_err(|_| DidEthError::InvalidMetaTransaction(format!("v = {}", signature.v)))?;
+        Ok(Self {
+            registry,
+            identity,
+            nonce,
+            operation,
+            v,
+            r: H256::from(u256_bytes(signature.r)),
+            s: H256::from(u256_bytes(signature.s)),
+        })
+    }
+
+    /// recover the address that signed this meta transaction
+    pub fn signer(&self) -> Result<Address, DidEthError> {
+        let hash = self
+            .operation
+            .hash(self.registry, self.nonce, self.identity)?;
+        let signature = Signature {
+            r: U256::from_big_endian(self.r.as_bytes()),
+            s: U256::from_big_endian(self.s.as_bytes()),
+            v: self.v.into(),
+        };
+        if self.v != 27 && self.v != 28 {
+            return Err(DidEthError::InvalidMetaTransaction(format!(
+                "invalid signature recovery id: {}",
+                self.v
+            )));
+        }
+        signature
+            .recover(RecoveryMessage::Hash(hash))
+            .map_err(|err| DidEthError::InvalidMetaTransaction(err.to_string()))
+    }
+}
+
+/// sign a sequence of operations for one identity with consecutive nonces starting at `nonce`
+/// the relay must submit the bundle in order
+pub fn sign_bundle(
+    wallet_key: &str,
+    registry: Address,
+    identity: Address,
+    nonce: U256,
+    operations: Vec<Operation>,
+) -> Result<Vec<SignedMetaTransaction>, DidEthError> {
+    let wallet = wallet_key.parse::<LocalWallet>()?;
+    operations
+        .into_iter()
+        .enumerate()
+        .map(|(i, operation)| {
+            SignedMetaTransaction::sign(&wallet, registry, identity, nonce + i, operation)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{signers::Signer, utils::format_bytes32_string};
+    use std::str::FromStr;
+
+    const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    fn registry() -> Address {
+        Address::from_str(crate::DID_ETH_REGISTRY).unwrap()
+    }
+
+    #[test]
+    fn test_payload_layout() {
+        let identity = Address::repeat_byte(0x11);
+        let operation = Operation::SetAttribute {
+            name: "did/svc/MessagingService".to_string(),
+            value: Bytes::from_static(b"https://example.com"),
+            validity: U256::from(86400),
+        };
+        let payload = operation
+            .payload(registry(), U256::from(7), identity)
+            .unwrap();
+        assert_eq!(&payload[0..2], &[0x19, 0x00]);
+        assert_eq!(&payload[2..22], registry().as_bytes());
+        assert_eq!(U256::from_big_endian(&payload[22..54]), U256::from(7));
+        assert_eq!(&payload[54..74], identity.as_bytes());
+        assert_eq!(&payload[74..86], b"setAttribute");
+        assert_eq!(
+            &payload[86..118],
+            &format_bytes32_string("did/svc/MessagingService").unwrap()
+        );
+        assert_eq!(&payload[118..137], b"https://example.com");
+        assert_eq!(U256::from_big_endian(&payload[137..]), U256::from(86400));
+    }
+
+    #[test]
+    fn test_change_owner_payload() {
+        let identity = Address::repeat_byte(0x11);
+        let new_owner = Address::repeat_byte(0x22);
+        let payload = Operation::ChangeOwner { new_owner }
+            .payload(registry(), U256::zero(), identity)
+            .unwrap();
+        assert_eq!(payload.len(), 2 + 20 + 32 + 20 + "changeOwner".len() + 20);
+        assert_eq!(&payload[payload.len() - 20..], new_owner.as_bytes());
+    }
+
+    #[test]
+    fn test_deactivate() {
+        let wallet = TEST_KEY.parse::<LocalWallet>().unwrap();
+        let identity = wallet.address();
+        let zero_owner = Operation::ChangeOwner {
+            new_owner: Address::zero(),
+        };
+        assert!(matches!(
+            SignedMetaTransaction::sign(&wallet, registry(), identity, U256::zero(), zero_owner.clone()),
+            Err(DidEthError::ZeroAddressOwner(rejected)) if rejected == identity
+        ));
+
+        // deactivation signs the registry's changeOwner to the zero address
+        let signed = SignedMetaTransaction::sign(
+            &wallet,
+            registry(),
+            identity,
+            U256::zero(),
+            Operation::Deactivate,
+        )
+        .unwrap();
+        assert_eq!(
+            Operation::Deactivate
+                .payload(registry(), U256::zero(), identity)
+                .unwrap(),
+            zero_owner
+                .payload(registry(), U256::zero(), identity)
+                .unwrap()
+        );
+        assert_eq!(signed.signer().unwrap(), identity);
+        assert_eq!(signed.operation.new_owner(), Some(Address::zero()));
+        let json = serde_json::to_value(&signed).unwrap();
+        assert_eq!(json["operation"]["type"], "deactivate");
+    }
+
+    #[test]
+    fn test_name_too_long() {
+        let operation = Operation::RevokeAttribute {
+            name: "did/svc/ThisServiceNameIsFarTooLong".to_string(),
+            value: Bytes::new(),
+        };
+        assert!(matches!(
+            operation.payload(registry(), U256::zero(), Address::zero()),
+            Err(DidEthError::AttributeNameTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_sign_and_recover() {
+        let wallet = TEST_KEY.parse::<LocalWallet>().unwrap();
+        let operation = Operation::AddDelegate {
+            delegate_type: DelegateType::SigAuth,
+            delegate: Address::repeat_byte(0x33),
+            validity: U256::from(3600),
+        };
+        let signed = SignedMetaTransaction::sign(
+            &wallet,
+            registry(),
+            wallet.address(),
+            U256::from(2),
+            operation,
+        )
+        .unwrap();
+        assert!(signed.v == 27 || signed.v == 28);
+        assert_eq!(signed.signer().unwrap(), wallet.address());
+
+        let mut tampered = signed.clone();
+        tampered.nonce = U256::from(3);
+        assert_ne!(tampered.signer().unwrap(), wallet.address());
+    }
+
+    #[test]
+    fn test_sign_bundle_nonces() {
+        let wallet = TEST_KEY.parse::<LocalWallet>().unwrap();
+        let operations = ["a", "b", "c"]
+            .iter()
+            .map(|name| Operation::RevokeAttribute {
+                name: name.to_string(),
+                value: Bytes::from_static(b"value"),
+            })
+            .collect();
+        let bundle = sign_bundle(
+            TEST_KEY,
+            registry(),
+            wallet.address(),
+            U256::from(5),
+            operations,
+        )
+        .unwrap();
+        assert_eq!(bundle.len(), 3);
+        for (i, signed) in bundle.iter().enumerate() {
+            assert_eq!(signed.nonce, U256::from(5 + i));
+            assert_eq!(signed.signer().unwrap(), wallet.address());
+        }
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let wallet = TEST_KEY.parse::<LocalWallet>().unwrap();
+        let signed = SignedMetaTransaction::sign(
+            &wallet,
+            registry(),
+            wallet.address(),
+            U256::zero(),
+            Operation::RevokeDelegate {
+                delegate_type: DelegateType::VeriKey,
+                delegate: Address::repeat_byte(0x44),
+            },
+        )
+        .unwrap();
+        let json = serde_json::to_value(&signed).unwrap();
+        assert_eq!(json["operation"]["type"], "revokeDelegate");
+        assert_eq!(json["operation"]["delegateType"], "veriKey");
+        let decoded: SignedMetaTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, signed);
+    }
+}
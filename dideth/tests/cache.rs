@@ -0,0 +1,134 @@
+//! Cached resolutions against the bundled DIDRegistry on an in-process chain
+
+use devchain::DevChain;
+use dideth::{
+    CachingResolver, Did, DidEthRegistry, MemoryCache, Network, RecordingTransport, RegistryConfig,
+    ResolutionCache, ResolutionOptions, Resolver, DEV_CHAIN_ID,
+};
+use ethers::{prelude::Provider, signers::Signer};
+use std::{sync::Arc, time::Duration};
+
+const SERVICE: &str = "did/svc/MessagingService";
+
+fn config() -> RegistryConfig {
+    RegistryConfig::for_network(Network::Dev).with_confirmations(1)
+}
+
+fn did() -> Did {
+    Did::new(DEV_CHAIN_ID, DevChain::wallet(0).address())
+}
+
+#[tokio::test]
+async fn test_repeat_resolution_costs_one_call() {
+    let dev_chain = DevChain::with_registry();
+    let registry = DidEthRegistry::from_client(Arc::new(dev_chain.signer(0)), config())
+        .await
+        .unwrap();
+    let receipt = registry
+        .set_attribute(SERVICE.to_string(), "https://example.com/a".to_string())
+        .await
+        .unwrap();
+
+    let recorder = RecordingTransport::new(dev_chain.clone());
+    let provider = Arc::new(Provider::new(recorder.clone()));
+    let resolver = Resolver::from_client(provider, config()).await.unwrap();
+    let cache = MemoryCache::new(16, Duration::from_secs(600));
+    let caching = CachingResolver::new(resolver, cache);
+    let latest = ResolutionOptions::latest();
+    let requests = || recorder.cassette().interactions.len();
+
+    let first = caching.resolve(&did().to_string(), &latest).await;
+    assert_eq!(first.did_document.as_ref().unwrap().service.len(), 1);
+    let entry = caching.cache().get(&did().to_string()).unwrap();
+    assert_eq!(entry.changed, receipt.block_number.unwrap());
+    assert_eq!(
+        entry.expires,
+        Some(entry.timestamp + config().attribute_lifetime)
+    );
+
+    let before = requests();
+    let second = caching.resolve(&did().to_string(), &latest).await;
+    assert_eq!(second, first);
+    assert_eq!(requests(), before + 1);
+    assert_eq!(
+        recorder.cassette().interactions.last().unwrap().method,
+        "eth_call"
+    );
+
+    // a new change moves the version key
+    registry
+        .set_attribute(SERVICE.to_string(), "https://example.com/b".to_string())
+        .await
+        .unwrap();
+    let third = caching.resolve(&did().to_string(), &latest).await;
+    assert_eq!(third.did_document.unwrap().service.len(), 2);
+
+    // explicit invalidation forces a full resolution
+    caching.invalidate(&did().to_string());
+    let before = requests();
+    caching.resolve(&did().to_string(), &latest).await;
+    assert!(requests() > before + 1);
+}
+
+#[tokio::test]
+async fn test_versions_and_failures_are_not_cached() {
+    let dev_chain = DevChain::with_registry();
+    let resolver = Resolver::from_client(Arc::new(dev_chain.provider()), config())
+        .await
+        .unwrap();
+    let caching = CachingResolver::new(resolver, MemoryCache::new(16, Duration::from_secs(600)));
+
+    let options = ResolutionOptions::latest().with_version_id(dev_chain.block_number());
+    let result = caching.resolve(&did().to_string(), &options).await;
+    assert!(result.did_document.is_some());
+    let result = caching
+        .resolve("did:ethr:0x1:not-an-address", &ResolutionOptions::latest())
+        .await;
+    assert!(result.did_resolution_metadata.error.is_some());
+    assert!(caching.cache().is_empty());
+}
+
+#[tokio::test]
+async fn test_each_did_form_is_cached_separately() {
+    let dev_chain = DevChain::with_registry();
+    let recorder = RecordingTransport::new(dev_chain.clone());
+    let provider = Arc::new(Provider::new(recorder.clone()));
+    let resolver = Resolver::from_client(provider, config()).await.unwrap();
+    let caching = CachingResolver::new(resolver, MemoryCache::new(16, Duration::from_secs(600)));
+    let latest = ResolutionOptions::latest();
+    let requests = || recorder.cassette().interactions.len();
+
+    let wallet = DevChain::wallet(0);
+    let public_key = wallet.signer().verifying_key().to_encoded_point(true);
+    let forms = [
+        (format!("did:ethr:dev:{:#x}", wallet.address()), false),
+        (did().to_string(), false),
+        (
+            format!("did:ethr:{DEV_CHAIN_ID:#x}:{:#x}", wallet.address()),
+            false,
+        ),
+        (
+            format!("did:ethr:dev:0x{}", hex::encode(public_key.as_bytes())),
+            true,
+        ),
+    ];
+    for (form, _) in &forms {
+        caching.resolve(form, &latest).await;
+    }
+    assert_eq!(caching.cache().len(), forms.len());
+
+    for (form, controller_key) in &forms {
+        let before = requests();
+        let result = caching.resolve(form, &latest).await;
+        assert_eq!(requests(), before + 1, "{form} is served from the cache");
+        let document = result.did_document.unwrap();
+        assert_eq!(&document.id, form);
+        assert_eq!(
+            document
+                .verification_method
+                .iter()
+                .any(|method| method.id == format!("{form}#controllerKey")),
+            *controller_key
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Resolutions recorded on an in-process chain and replayed without it
+
+use devchain::DevChain;
+use dideth::{
+    Cassette, DidEthRegistry, Network, RecordingTransport, RegistryConfig, ReplayTransport,
+    ResolutionOptions, ResolutionResult, Resolver, DEV_CHAIN_ID,
+};
+use ethers::{prelude::Provider, signers::Signer};
+use std::{path::PathBuf, sync::Arc};
+
+const SERVICE: &str = "did/svc/MessagingService";
+const ENDPOINT: &str = "https://example.com/messaging";
+
+fn config() -> RegistryConfig {
+    RegistryConfig::for_network(Network::Dev).with_confirmations(1)
+}
+
+fn did() -> String {
+    format!(
+        "did:ethr:{DEV_CHAIN_ID:#x}:{:#x}",
+        DevChain::wallet(0).address()
+    )
+}
+
+fn fixture() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/resolution.json")
+}
+
+/// resolve `did()` after publishing a service, recording every request
+async fn record(dev_chain: &DevChain) -> (ResolutionResult, Cassette) {
+    let registry = DidEthRegistry::from_client(Arc::new(dev_chain.signer(0)), config())
+        .await
+        .unwrap();
+    registry
+        .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+
+    let recorder = RecordingTransport::new(dev_chain.clone());
+    let provider = Arc::new(Provider::new(recorder.clone()));
+    let resolver = Resolver::from_client(provider, config()).await.unwrap();
+    let result = resolver.resolve(&did(), &ResolutionOptions::latest()).await;
+    (result, recorder.cassette())
+}
+
+async fn replay(cassette: Cassette) -> ResolutionResult {
+    let provider = Arc::new(Provider::new(ReplayTransport::new(cassette)));
+    let resolver = Resolver::from_client(provider, config()).await.unwrap();
+    resolver.resolve(&did(), &ResolutionOptions::latest()).await
+}
+
+#[tokio::test]
+async fn test_replay_matches_recording() {
+    let dev_chain = DevChain::with_registry();
+    let (recorded, cassette) = record(&dev_chain).await;
+    assert!(recorded.did_resolution_metadata.error.is_none());
+    assert!(cassette
+        .interactions
+        .iter()
+        .any(|interaction| interaction.method == "eth_getLogs"));
+
+    // later changes on chain do not affect the replay
+    dev_chain.advance_time(86400 * 400);
+    assert_eq!(replay(cassette).await, recorded);
+}
+
+#[tokio::test]
+async fn test_replay_fixture() {
+    // RECORD_FIXTURES=1 cargo test --test cassette re-records the fixture
+    if std::env::var("RECORD_FIXTURES").is_ok() {
+        let (_, cassette) = record(&DevChain::with_registry()).await;
+        cassette.save(fixture()).unwrap();
+    }
+    let cassette = Cassette::load(fixture()).unwrap();
+    let result = replay(cassette).await;
+    let document = result.did_document.unwrap();
+    assert_eq!(document.service.len(), 1);
+    assert_eq!(document.service[0].service_endpoint, ENDPOINT);
+    assert_eq!(
+        result.did_document_metadata.version_id,
+        Some("2".to_string())
+    );
+}
@@ -0,0 +1,383 @@
+//! Registry flows against the bundled DIDRegistry deployed on an in-process chain
+
+use devchain::DevChain;
+use dideth::{
+    sign_bundle, verify_registry, DelegateType, Did, DidEthError, DidEthRegistry, InMemoryRegistry,
+    Network, Operation, RegistryBackend, RegistryConfig, RegistryEvent, ResolutionOptions,
+    DEV_CHAIN_ID,
+};
+use ethers::{
+    prelude::{LocalWallet, Provider, SignerMiddleware},
+    signers::Signer,
+    types::{Address, U256},
+    utils::to_checksum,
+};
+use std::sync::Arc;
+
+type DevRegistry = DidEthRegistry<SignerMiddleware<Provider<DevChain>, LocalWallet>>;
+
+const SERVICE: &str = "did/svc/MessagingService";
+const ENDPOINT: &str = "https://example.com/messaging";
+
+fn config() -> RegistryConfig {
+    // every transaction is mined in its own block immediately
+    RegistryConfig::for_network(Network::Dev).with_confirmations(1)
+}
+
+async fn registry(dev_chain: &DevChain, account: usize, config: RegistryConfig) -> DevRegistry {
+    DidEthRegistry::from_client(Arc::new(dev_chain.signer(account)), config)
+        .await
+        .unwrap()
+}
+
+fn address(account: usize) -> Address {
+    DevChain::wallet(account).address()
+}
+
+fn did(account: usize) -> Did {
+    Did::new(DEV_CHAIN_ID, address(account))
+}
+
+#[tokio::test]
+async fn test_registry_is_well_known_deployment() {
+    let dev_chain = DevChain::with_registry();
+    assert_eq!(dev_chain.registry(), Some(Network::Dev.registry()));
+}
+
+#[tokio::test]
+async fn test_deploy_registry() {
+    let dev_chain = DevChain::new();
+    let client = Arc::new(dev_chain.signer(1));
+    let registry = DevRegistry::deploy_from_client(client.clone(), config())
+        .await
+        .unwrap();
+    assert_ne!(registry.registry_address(), Network::Dev.registry());
+    assert_eq!(registry.config().registry, registry.registry_address());
+    verify_registry(client.as_ref(), registry.registry_address())
+        .await
+        .unwrap();
+    assert!(matches!(
+        verify_registry(client.as_ref(), address(0)).await,
+        Err(DidEthError::RegistryCodeMismatch(registry)) if registry == address(0)
+    ));
+
+    let owner = registry.owner(&did(1)).await.unwrap();
+    assert_eq!(owner.parse::<Address>().unwrap(), address(1));
+    registry
+        .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+    assert_eq!(registry.attribute(&did(1)).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_attribute_lifecycle() {
+    let dev_chain = DevChain::with_registry();
+    let registry = registry(&dev_chain, 0, config()).await;
+
+    let receipt = registry
+        .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+    assert!(receipt.is_success());
+    assert_eq!(receipt.attribute_changed.unwrap().name, SERVICE);
+    assert_eq!(
+        registry.attribute(&did(0)).await.unwrap(),
+        vec![(SERVICE.to_string(), ENDPOINT.to_string())]
+    );
+    let document = registry.resolver().document(&did(0)).await.unwrap();
+    assert_eq!(document.service.len(), 1);
+    assert_eq!(document.service[0].service_endpoint, ENDPOINT);
+
+    registry
+        .revoke_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+    assert!(registry.attribute(&did(0)).await.unwrap().is_empty());
+    let document = registry.resolver().document(&did(0)).await.unwrap();
+    assert!(document.service.is_empty());
+}
+
+#[tokio::test]
+async fn test_attribute_expiry() {
+    let dev_chain = DevChain::with_registry();
+    let registry = registry(&dev_chain, 0, config().with_attribute_lifetime(100)).await;
+    registry
+        .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+    assert_eq!(registry.attribute(&did(0)).await.unwrap().len(), 1);
+
+    dev_chain.advance_time(200);
+    assert!(registry.attribute(&did(0)).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_delegate_lifecycle() {
+    let dev_chain = DevChain::with_registry();
+    let registry = registry(&dev_chain, 0, config()).await;
+    let delegate = format!("{:#x}", address(1));
+
+    registry
+        .add_delegate(DelegateType::SigAuth, delegate.clone(), 3600)
+        .await
+        .unwrap();
+    assert!(registry
+        .valid_delegate(&did(0), DelegateType::SigAuth, delegate.clone())
+        .await
+        .unwrap());
+    assert_eq!(
+        registry.resolver().delegates(&did(0)).await.unwrap(),
+        vec![(DelegateType::SigAuth, address(1))]
+    );
+    let document = registry.resolver().document(&did(0)).await.unwrap();
+    assert_eq!(document.verification_method.len(), 2);
+    assert_eq!(document.authentication.len(), 2);
+
+    registry
+        .revoke_delegate(DelegateType::SigAuth, delegate.clone())
+        .await
+        .unwrap();
+    // the revoked delegate is valid until the revocation timestamp has passed
+    dev_chain.mine();
+    assert!(!registry
+        .valid_delegate(&did(0), DelegateType::SigAuth, delegate)
+        .await
+        .unwrap());
+    let document = registry.resolver().document(&did(0)).await.unwrap();
+    assert_eq!(document.verification_method.len(), 1);
+}
+
+#[tokio::test]
+async fn test_change_owner() {
+    let dev_chain = DevChain::with_registry();
+    let registry = registry(&dev_chain, 0, config()).await;
+    let new_owner = format!("{:#x}", address(1));
+
+    registry
+        .change_owner(&did(0), new_owner.clone())
+        .await
+        .unwrap();
+    let owner = registry.owner(&did(0)).await.unwrap();
+    assert_eq!(owner.parse::<Address>().unwrap(), address(1));
+    assert!(matches!(
+        registry.change_owner(&did(0), new_owner).await,
+        Err(DidEthError::NotOwner { .. })
+    ));
+
+    let history = registry.resolver().history(&did(0)).await.unwrap();
+    assert!(matches!(
+        history.last().unwrap().registry_event(),
+        RegistryEvent::OwnerChanged(event) if event.owner == address(1)
+    ));
+    let document = registry.resolver().document(&did(0)).await.unwrap();
+    let controller = document.verification_method[0]
+        .blockchain_account_id
+        .clone()
+        .unwrap();
+    assert!(controller.ends_with(&ethers::utils::to_checksum(&address(1), None)));
+}
+
+#[tokio::test]
+async fn test_deactivate() {
+    let dev_chain = DevChain::with_registry();
+    let registry = registry(&dev_chain, 0, config()).await;
+
+    registry.deactivate(&did(0)).await.unwrap();
+    assert!(registry.resolver().is_deactivated(&did(0)).await.unwrap());
+    assert!(matches!(
+        registry
+            .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+            .await,
+        Err(DidEthError::Deactivated(identity)) if identity == address(0)
+    ));
+    let result = registry
+        .resolver()
+        .resolve(&did(0).to_string(), &ResolutionOptions::latest())
+        .await;
+    assert_eq!(result.did_document_metadata.deactivated, Some(true));
+}
+
+#[tokio::test]
+async fn test_relay_signed_operations() {
+    let dev_chain = DevChain::with_registry();
+    // account 3 pays for the meta transactions signed by account 2
+    let relay = registry(&dev_chain, 3, config()).await;
+    let nonce = relay.nonce(&did(2)).await.unwrap();
+    let bundle = sign_bundle(
+        devchain::DEV_KEYS[2],
+        relay.registry_address(),
+        address(2),
+        nonce,
+        vec![
+            Operation::SetAttribute {
+                name: SERVICE.to_string(),
+                value: ENDPOINT.as_bytes().to_vec().into(),
+                validity: U256::from(3600),
+            },
+            Operation::AddDelegate {
+                delegate_type: DelegateType::VeriKey,
+                delegate: address(1),
+                validity: U256::from(3600),
+            },
+        ],
+    )
+    .unwrap();
+    for signed in bundle.iter() {
+        assert!(relay.submit_signed(signed).await.unwrap().is_success());
+    }
+
+    assert_eq!(relay.nonce(&did(2)).await.unwrap(), nonce + 2);
+    assert_eq!(
+        relay.attribute(&did(2)).await.unwrap(),
+        vec![(SERVICE.to_string(), ENDPOINT.to_string())]
+    );
+    assert!(relay
+        .valid_delegate(&did(2), DelegateType::VeriKey, format!("{:#x}", address(1)))
+        .await
+        .unwrap());
+    // replaying a relayed operation is rejected before it is sent
+    assert!(matches!(
+        relay.submit_signed(&bundle[0]).await,
+        Err(DidEthError::InvalidMetaTransaction(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_relay_signed_deactivation() {
+    let dev_chain = DevChain::with_registry();
+    let relay = registry(&dev_chain, 3, config()).await;
+    let nonce = relay.nonce(&did(2)).await.unwrap();
+    let bundle = sign_bundle(
+        devchain::DEV_KEYS[2],
+        relay.registry_address(),
+        address(2),
+        nonce,
+        vec![Operation::Deactivate],
+    )
+    .unwrap();
+
+    // the same signature relayed as a zero address owner change is refused
+    let mut zero_owner = bundle[0].clone();
+    zero_owner.operation = Operation::ChangeOwner {
+        new_owner: Address::zero(),
+    };
+    assert!(matches!(
+        relay.submit_signed(&zero_owner).await,
+        Err(DidEthError::ZeroAddressOwner(_))
+    ));
+    assert!(!relay.resolver().is_deactivated(&did(2)).await.unwrap());
+
+    assert!(relay.submit_signed(&bundle[0]).await.unwrap().is_success());
+    assert!(relay.resolver().is_deactivated(&did(2)).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_resolve_keeps_did_spelling() {
+    let dev_chain = DevChain::with_registry();
+    let resolver = registry(&dev_chain, 0, config()).await;
+    let resolver = resolver.resolver();
+    let public_key = DevChain::wallet(0)
+        .signer()
+        .verifying_key()
+        .to_encoded_point(true);
+    let spellings = [
+        (format!("did:ethr:dev:{:#x}", address(0)), false),
+        (
+            format!(
+                "did:ethr:{DEV_CHAIN_ID:#x}:{}",
+                to_checksum(&address(0), None)
+            ),
+            false,
+        ),
+        (
+            format!("did:ethr:dev:0x{}", hex::encode(public_key.as_bytes())),
+            true,
+        ),
+    ];
+    for (id, controller_key) in spellings {
+        let result = resolver.resolve(&id, &ResolutionOptions::latest()).await;
+        let document = result.did_document.unwrap();
+        assert_eq!(document.id, id);
+        let mut expected = vec![format!("{id}#controller")];
+        if controller_key {
+            expected.push(format!("{id}#controllerKey"));
+        }
+        assert_eq!(document.authentication, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_version_id() {
+    let dev_chain = DevChain::with_registry();
+    let registry = registry(&dev_chain, 0, config()).await;
+    let before = dev_chain.block_number();
+    registry
+        .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+
+    let resolver = registry.resolver();
+    let options = ResolutionOptions::latest().with_version_id(before);
+    let document = resolver.document_at(&did(0), &options).await.unwrap();
+    assert!(document.service.is_empty());
+    let result = resolver
+        .resolve(&did(0).to_string(), &ResolutionOptions::latest())
+        .await;
+    assert_eq!(result.did_document.unwrap().service.len(), 1);
+    assert_eq!(
+        result.did_document_metadata.version_id,
+        Some((before + 1).to_string())
+    );
+}
+
+/// the same writes through either backend, returning what a consumer would read back
+async fn scenario(backend: &dyn RegistryBackend) -> Vec<String> {
+    let identity = did(0);
+    let delegate = format!("{:#x}", address(1));
+    backend
+        .set_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+    backend
+        .add_delegate(DelegateType::VeriKey, delegate.clone(), 3600)
+        .await
+        .unwrap();
+    backend
+        .revoke_attribute(SERVICE.to_string(), ENDPOINT.to_string())
+        .await
+        .unwrap();
+    backend
+        .change_owner(&identity, format!("{:#x}", address(2)))
+        .await
+        .unwrap();
+    let history = backend.history(&identity).await.unwrap();
+    vec![
+        backend.owner(&identity).await.unwrap(),
+        format!("{:?}", backend.attribute(&identity).await.unwrap()),
+        format!("{:?}", backend.delegates(&identity).await.unwrap()),
+        format!(
+            "{}",
+            backend
+                .valid_delegate(&identity, DelegateType::VeriKey, delegate)
+                .await
+                .unwrap()
+        ),
+        backend.nonce(&identity).await.unwrap().to_string(),
+        serde_json::to_string(&history).unwrap(),
+    ]
+}
+
+#[tokio::test]
+async fn test_in_memory_registry_matches_chain() {
+    let dev_chain = DevChain::with_registry();
+    let memory = InMemoryRegistry::new(address(0));
+    // start both registries from the same block and timestamp
+    while memory.block_number() < dev_chain.block_number() {
+        memory.mine();
+    }
+    assert_eq!(memory.timestamp(), dev_chain.timestamp());
+
+    let on_chain = registry(&dev_chain, 0, config()).await;
+    assert_eq!(scenario(&memory).await, scenario(&on_chain).await);
+}
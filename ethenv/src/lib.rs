@@ -1,4 +1,9 @@
-use std::env;
+use dideth::{sign_bundle, Did, DidEthError, Operation, RegistryConfig, SignedMetaTransaction};
+use std::{
+    env, fs,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
 
 pub struct Environment {
     pub rpc_url: String,
@@ -7,25 +12,200 @@ pub struct Environment {
     pub attribute: Vec<(String, String)>,
 }
 
+/// environment for signing meta transactions on an offline machine, no RPC_URL is needed
+pub struct SigningEnvironment {
+    pub public_key: String,
+    pub private_key: String,
+    pub attribute: Vec<(String, String)>,
+    pub nonce: u64,
+}
+
+/// environment for relaying signed meta transactions with a funded wallet
+pub struct RelayEnvironment {
+    pub rpc_url: String,
+    pub private_key: String,
+}
+
+/// environment for read-only resolution, no signing secret is needed
+pub struct ResolverEnvironment {
+    pub rpc_url: String,
+    pub public_key: String,
+    /// optional directory for cached resolutions
+    pub resolution_cache: Option<String>,
+}
+
+/// environment for replaying a recorded resolution offline, no RPC_URL is needed
+pub struct ReplayEnvironment {
+    pub public_key: String,
+}
+
+/// optional registry selection, by default the sepolia did:eth registry is used
+pub struct RegistrySettings {
+    pub network: Option<String>,
+    pub registry_address: Option<String>,
+}
+
 pub fn init() {
     dotenv::dotenv().ok();
 }
 
 pub fn environment() -> Environment {
-    let attribute = env::var("ATTRIBUTE").expect("ATTRIBUTE must be set");
-    let attribute_vec = attribute.split(',').collect::<Vec<&str>>();
-    let attribute_pair = attribute_vec
-        .iter()
-        .map(|x| {
-            let pair = x.split('=').collect::<Vec<&str>>();
-            (pair[0].trim().to_string(), pair[1].trim().to_string())
-        })
-        .collect::<Vec<(String, String)>>();
+    let attribute = attribute_environment();
     Environment {
         rpc_url: env::var("RPC_URL").expect("RPC_URL must be set"),
         public_key: env::var("PUBLIC_KEY").expect("PUBLIC_KEY must be set"),
         private_key: env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set"),
-        attribute: attribute_pair,
+        attribute,
+    }
+}
+
+pub fn signing_environment() -> SigningEnvironment {
+    let attribute = attribute_environment();
+    let nonce = env::var("NONCE").expect("NONCE must be set");
+    SigningEnvironment {
+        public_key: env::var("PUBLIC_KEY").expect("PUBLIC_KEY must be set"),
+        private_key: env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set"),
+        attribute,
+        nonce: nonce.trim().parse().expect("NONCE must be a number"),
+    }
+}
+
+pub fn relay_environment() -> RelayEnvironment {
+    RelayEnvironment {
+        rpc_url: env::var("RPC_URL").expect("RPC_URL must be set"),
+        private_key: env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set"),
+    }
+}
+
+pub fn resolver_environment() -> ResolverEnvironment {
+    ResolverEnvironment {
+        rpc_url: env::var("RPC_URL").expect("RPC_URL must be set"),
+        public_key: env::var("PUBLIC_KEY").expect("PUBLIC_KEY must be set"),
+        resolution_cache: env::var("RESOLUTION_CACHE").ok(),
+    }
+}
+
+pub fn replay_environment() -> ReplayEnvironment {
+    ReplayEnvironment {
+        public_key: env::var("PUBLIC_KEY").expect("PUBLIC_KEY must be set"),
+    }
+}
+
+pub fn registry_settings() -> RegistrySettings {
+    RegistrySettings {
+        network: env::var("NETWORK").ok(),
+        registry_address: env::var("REGISTRY_ADDRESS").ok(),
+    }
+}
+
+/// the registry selected by `NETWORK` and `REGISTRY_ADDRESS`
+pub fn registry_config() -> Result<RegistryConfig, DidEthError> {
+    let settings = registry_settings();
+    RegistryConfig::from_settings(
+        settings.network.as_deref(),
+        settings.registry_address.as_deref(),
+    )
+}
+
+/// sign one operation per attribute offline, with consecutive nonces from `NONCE`
+pub fn sign_attributes(
+    env: &SigningEnvironment,
+    config: &RegistryConfig,
+    operation: impl Fn(&str, &str) -> Operation,
+) -> Result<Vec<SignedMetaTransaction>, DidEthError> {
+    let identity = match config.chain_id {
+        Some(chain_id) => Did::parse_on_chain(&env.public_key, chain_id)?,
+        None => env
+            .public_key
+            .parse::<Did>()
+            .map_err(|_| DidEthError::MissingChainId(env.public_key.clone()))?,
+    }
+    .address();
+    let operations = env
+        .attribute
+        .iter()
+        .map(|(name, value)| operation(name, value))
+        .collect();
+    sign_bundle(
+        &env.private_key,
+        config.registry,
+        identity,
+        env.nonce.into(),
+        operations,
+    )
+}
+
+/// write a signed bundle for `relay`
+pub fn write_bundle(path: &Path, bundle: &[SignedMetaTransaction]) -> io::Result<()> {
+    let writer = BufWriter::new(fs::File::create(path)?);
+    Ok(serde_json::to_writer_pretty(writer, bundle)?)
+}
+
+/// read a bundle written by `write_bundle`
+pub fn read_bundle(path: &Path) -> io::Result<Vec<SignedMetaTransaction>> {
+    let reader = BufReader::new(fs::File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// set `key=value` in the env file at `path`, replacing an existing assignment
+pub fn write_env_var(path: &Path, key: &str, value: &str) -> io::Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    fs::write(path, replace_var(&contents, key, value))
+}
+
+fn replace_var(contents: &str, key: &str, value: &str) -> String {
+    let assignment = format!("{}={}", key, value);
+    let mut replaced = false;
+    let mut lines = contents
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((name, _)) if name.trim() == key && !replaced => {
+                replaced = true;
+                assignment.clone()
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<String>>();
+    if !replaced {
+        lines.push(assignment);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// the ATTRIBUTE entries followed by the optional SERVICE entry, the SERVICE value is
+/// taken as is so a JSON service endpoint may contain commas
+fn attribute_environment() -> Vec<(String, String)> {
+    let attribute = env::var("ATTRIBUTE").expect("ATTRIBUTE must be set");
+    let mut attribute = parse_attribute(&attribute)
+        .unwrap_or_else(|entry| panic!("ATTRIBUTE entry {entry:?} must be name=value"));
+    if let Ok(service) = env::var("SERVICE") {
+        let service = parse_entry(&service)
+            .unwrap_or_else(|entry| panic!("SERVICE {entry:?} must be did/svc/<type>=<endpoint>"));
+        attribute.push(service);
+    }
+    attribute
+}
+
+/// comma separated `name=value` entries, a malformed entry is returned as the error
+fn parse_attribute(attribute: &str) -> Result<Vec<(String, String)>, String> {
+    attribute
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(parse_entry)
+        .collect()
+}
+
+/// a `name=value` entry, the value may itself contain `=`
+fn parse_entry(entry: &str) -> Result<(String, String), String> {
+    match entry.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(entry.to_string()),
     }
 }
 
@@ -112,6 +292,123 @@ mod tests {
         environment();
     }
 
+    #[test]
+    fn test_parse_attribute() {
+        let attribute =
+            parse_attribute("did/svc/MessagingService = https://example.com,name=value,").unwrap();
+        assert_eq!(attribute.len(), 2);
+        assert_eq!(attribute[0].0, "did/svc/MessagingService");
+        assert_eq!(attribute[0].1, "https://example.com");
+        assert_eq!(attribute[1].0, "name");
+        assert_eq!(attribute[1].1, "value");
+
+        let attribute = parse_attribute("did/pub/Secp256k1/veriKey/base64=AAAA==").unwrap();
+        assert_eq!(attribute[0].1, "AAAA==");
+        assert_eq!(
+            parse_attribute("name=value,profile"),
+            Err("profile".to_string())
+        );
+        assert_eq!(parse_attribute("=value"), Err("=value".to_string()));
+    }
+
+    #[test]
+    fn test_parse_service_entry() {
+        let endpoint =
+            r#"{"uri":"https://example.com/messaging","accept":["didcomm/v2","didcomm/aip2"]}"#;
+        let service = parse_entry(&format!("did/svc/MessagingService={endpoint}")).unwrap();
+        assert_eq!(service.0, "did/svc/MessagingService");
+        assert_eq!(service.1, endpoint);
+    }
+
+    // hardhat dev account 0
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    fn signing() -> SigningEnvironment {
+        SigningEnvironment {
+            public_key: ADDRESS.to_string(),
+            private_key: KEY.to_string(),
+            attribute: vec![
+                ("name".to_string(), "value".to_string()),
+                ("name2".to_string(), "value2".to_string()),
+            ],
+            nonce: 3,
+        }
+    }
+
+    fn revoke(name: &str, value: &str) -> Operation {
+        Operation::RevokeAttribute {
+            name: name.to_string(),
+            value: value.as_bytes().to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn test_sign_attributes() {
+        let config = RegistryConfig::for_network(dideth::Network::Sepolia);
+        let bundle = sign_attributes(&signing(), &config, revoke).unwrap();
+        assert_eq!(bundle.len(), 2);
+        for (i, signed) in bundle.iter().enumerate() {
+            assert_eq!(signed.registry, config.registry);
+            assert_eq!(format!("{:#x}", signed.identity), ADDRESS);
+            assert_eq!(signed.nonce, (3 + i).into());
+            assert_eq!(format!("{:#x}", signed.signer().unwrap()), ADDRESS);
+        }
+        assert_eq!(bundle[1].operation, revoke("name2", "value2"));
+
+        let path = env::temp_dir().join(format!("ethenv-bundle-{}.json", std::process::id()));
+        write_bundle(&path, &bundle).unwrap();
+        assert_eq!(read_bundle(&path).unwrap(), bundle);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sign_attributes_errors() {
+        let config = RegistryConfig::default();
+        let mut env = signing();
+        env.public_key = "not a key".to_string();
+        assert!(matches!(
+            sign_attributes(&env, &config, revoke),
+            Err(DidEthError::InvalidDid(_))
+        ));
+        let mut env = signing();
+        env.attribute = vec![(
+            "did/svc/ThisServiceNameIsFarTooLong".to_string(),
+            String::new(),
+        )];
+        assert!(matches!(
+            sign_attributes(&env, &config, revoke),
+            Err(DidEthError::AttributeNameTooLong(_))
+        ));
+        // a bare address needs the chain it is on
+        let config = RegistryConfig {
+            chain_id: None,
+            ..config
+        };
+        assert!(matches!(
+            sign_attributes(&signing(), &config, revoke),
+            Err(DidEthError::MissingChainId(_))
+        ));
+        let mut env = signing();
+        env.public_key = format!("did:ethr:sepolia:{ADDRESS}");
+        assert_eq!(sign_attributes(&env, &config, revoke).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_replace_var() {
+        let contents =
+            "RPC_URL=http://localhost:8545\n#REGISTRY_ADDRESS=0x1\nREGISTRY_ADDRESS=0x2\n";
+        assert_eq!(
+            replace_var(contents, "REGISTRY_ADDRESS", "0x3"),
+            "RPC_URL=http://localhost:8545\n#REGISTRY_ADDRESS=0x1\nREGISTRY_ADDRESS=0x3\n"
+        );
+        assert_eq!(
+            replace_var("RPC_URL=http://localhost:8545", "REGISTRY_ADDRESS", "0x3"),
+            "RPC_URL=http://localhost:8545\nREGISTRY_ADDRESS=0x3\n"
+        );
+        assert_eq!(replace_var("", "NETWORK", "dev"), "NETWORK=dev\n");
+    }
+
     #[test]
     fn test_scram() {
         assert_eq!(scram("12345678901".to_string()), "**********");
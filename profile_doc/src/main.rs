@@ -1,10 +1,85 @@
-use dideth::DidEthRegistry;
-use ethenv::{environment, init, printenv};
+use dideth::{
+    CachingResolver, DiskCache, RecordingTransport, RegistryConfig, ReplayTransport,
+    ResolutionOptions, Resolver,
+};
+use ethenv::{init, registry_config, replay_environment, resolver_environment};
+use ethers::providers::{Http, Ipc, JsonRpcClient, Middleware, Provider, Ws};
+use std::{str::FromStr, sync::Arc};
 
-use serde_json::json;
+fn usage() {
+    println!("usage: profile_doc [--record <cassette.json> | --replay <cassette.json>]");
+    println!("                   [--version-id <block>] [--version-time <iso 8601>]");
+}
+
+/// where rpc responses come from, a cassette replays a recorded session offline
+enum Session {
+    Live,
+    Record(String),
+    Replay(String),
+}
+
+fn session(args: &[String]) -> (Session, &[String]) {
+    match args {
+        [flag, path, rest @ ..] if flag == "--record" => (Session::Record(path.clone()), rest),
+        [flag, path, rest @ ..] if flag == "--replay" => (Session::Replay(path.clone()), rest),
+        _ => (Session::Live, args),
+    }
+}
+
+fn resolution_options(args: &[String]) -> Option<ResolutionOptions> {
+    match args {
+        [] => Some(ResolutionOptions::latest()),
+        [flag, version_id] if flag == "--version-id" => version_id
+            .parse()
+            .ok()
+            .map(|version_id| ResolutionOptions::latest().with_version_id(version_id)),
+        [flag, version_time] if flag == "--version-time" => {
+            ResolutionOptions::parse_version_time(version_time)
+                .ok()
+                .map(|version_time| ResolutionOptions::latest().with_version_time(version_time))
+        }
+        _ => None,
+    }
+}
 
-fn fmt_as_did(public_key: String) -> String {
-    format!("did:ethr:{}", public_key)
+async fn profile_doc<M: Middleware + 'static>(
+    resolver: Resolver<M>,
+    public_key: String,
+    options: ResolutionOptions,
+    cache: Option<DiskCache>,
+) -> serde_json::Result<()> {
+    // bare addresses and public keys are resolved on the connected chain
+    let did = resolver
+        .did(&public_key)
+        .map(|did| did.to_string())
+        .unwrap_or(public_key);
+    let resolution = match cache {
+        Some(cache) => {
+            CachingResolver::new(resolver, cache)
+                .resolve(&did, &options)
+                .await
+        }
+        None => resolver.resolve(&did, &options).await,
+    };
+    serde_json::to_writer_pretty(std::io::stdout(), &resolution)?;
+    Ok(())
+}
+
+/// resolve through `transport`, saving every request and response to `path`
+async fn record<T: JsonRpcClient + Clone + 'static>(
+    transport: T,
+    config: RegistryConfig,
+    public_key: String,
+    options: ResolutionOptions,
+    path: &str,
+) -> serde_json::Result<()> {
+    let recorder = RecordingTransport::new(transport);
+    let provider = Arc::new(Provider::new(recorder.clone()));
+    let resolver = Resolver::from_client(provider, config).await.unwrap();
+    profile_doc(resolver, public_key, options, None).await?;
+    recorder.cassette().save(path).unwrap();
+    eprintln!("cassette: {}", path);
+    Ok(())
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -12,49 +87,54 @@ async fn main() -> serde_json::Result<()> {
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::DEBUG)
         .init();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let (session, args) = session(&args);
+    let Some(options) = resolution_options(args) else {
+        usage();
+        return Ok(());
+    };
     init();
-    let env = environment();
-    printenv(&env);
-
-    let public_key = env.public_key.clone();
-    let contract = DidEthRegistry::new(env.rpc_url, env.private_key)
-        .await
-        .unwrap();
-    let owner_id = contract.owner(public_key.clone()).await.unwrap();
-
-    let owner_attribute_vec = contract.attribute(owner_id.clone()).await.unwrap();
-    let did = fmt_as_did(public_key.clone());
-
-    let mut xmtp_service = std::collections::HashMap::<String, String>::new();
-    xmtp_service.insert("id".to_string(), did.clone());
-    xmtp_service.insert("type".to_string(), "MessagingService".to_string());
-    xmtp_service.insert("recipientKeys".to_string(), public_key.clone());
-    xmtp_service.insert(
-        "serviceEndpoint".to_string(),
-        "https://xmtp.com/resolver".to_string(),
-    );
-
-    owner_attribute_vec.iter().for_each(|(key, value)| {
-        xmtp_service.insert(key.clone(), value.clone());
-    });
-
-    let did_document = json!({
-        "@context": [
-            "https://www.w3.org/ns/did/v1",
-            "https://w3id.org/security/suites/ed25519-2020/v1"
-        ],
-        "controller": format!("{}", did.clone()),
-        "id": format!("{}", did.clone()),
-        "verificationMethod": [
-            {
-                "id": format!("{}", did.clone()),
-                "type": "Ed25519VerificationKey2020",
-                "controller": format!("{}", did.clone()),
-                "publicKeyMultibase": format!("{}", public_key.clone()),
-            }
-        ],
-        "service": [ xmtp_service ],
-    });
-    serde_json::to_writer_pretty(std::io::stdout(), &did_document)?;
-    Ok(())
+
+    let config = match registry_config() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("profile_doc: {err}");
+            std::process::exit(1);
+        }
+    };
+    if let Session::Replay(path) = session {
+        let env = replay_environment();
+        let provider = Provider::new(ReplayTransport::load(path).unwrap());
+        let resolver = Resolver::from_client(Arc::new(provider), config)
+            .await
+            .unwrap();
+        return profile_doc(resolver, env.public_key, options, None).await;
+    }
+
+    let env = resolver_environment();
+    println!("rpc_url: {}", env.rpc_url.split("v2").next().unwrap());
+    if let Session::Record(path) = session {
+        if env.rpc_url.starts_with("ws") {
+            let ws = Ws::connect(env.rpc_url).await.unwrap();
+            record(ws, config, env.public_key, options, &path).await
+        } else if env.rpc_url.starts_with("http") {
+            let http = Http::from_str(&env.rpc_url).unwrap();
+            record(http, config, env.public_key, options, &path).await
+        } else {
+            let ipc = Ipc::connect(&env.rpc_url).await.unwrap();
+            record(ipc, config, env.public_key, options, &path).await
+        }
+    } else {
+        let cache = env.resolution_cache.map(DiskCache::new);
+        if env.rpc_url.starts_with("ws") {
+            let resolver = Resolver::connect_ws(env.rpc_url, config).await.unwrap();
+            profile_doc(resolver, env.public_key, options, cache).await
+        } else if env.rpc_url.starts_with("http") {
+            let resolver = Resolver::connect_http(&env.rpc_url, config).await.unwrap();
+            profile_doc(resolver, env.public_key, options, cache).await
+        } else {
+            let resolver = Resolver::connect_ipc(&env.rpc_url, config).await.unwrap();
+            profile_doc(resolver, env.public_key, options, cache).await
+        }
+    }
 }
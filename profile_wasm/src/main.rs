@@ -4,6 +4,7 @@ use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew::{Html, Properties};
 
+use dideth::{Did, SEPOLIA_CHAIN_ID};
 use ethenv::scram;
 
 #[derive(Properties, Clone, PartialEq)]
@@ -109,7 +110,7 @@ fn app() -> Html {
         public_key: "".to_string(),
         private_key: "".to_string(),
         rpc_url: "https://eth-sepolia.g.alchemy.com/v2".to_string(),
-        did: "did:eth:0x1234567890".to_string(),
+        did: "".to_string(),
         owner: "0x1234567890".to_string(),
         attributes: vec![
             AttributeProps {
@@ -128,8 +129,12 @@ fn app() -> Html {
     let on_public_key = {
         let did_prop = did_prop.clone();
         Callback::from(move |value: String| {
+            let did = Did::parse_on_chain(&value, SEPOLIA_CHAIN_ID)
+                .map(|did| did.to_string())
+                .unwrap_or_default();
             did_prop.set(DidDocumentProps {
                 public_key: value.clone(),
+                did,
                 ..(*did_prop).clone()
             });
             info!("public key: {:?}", value);
@@ -0,0 +1,93 @@
+use dideth::DidEthRegistry;
+use ethenv::{init, read_bundle, registry_config, relay_environment, scram, write_env_var};
+use std::path::Path;
+
+fn usage() {
+    println!("usage: relay <bundle.json>");
+    println!("       relay --nonce <identity>");
+    println!("       relay --deploy [env file]");
+}
+
+enum Command {
+    Relay(String),
+    Nonce(String),
+    Deploy(Option<String>),
+}
+
+fn command(args: &[String]) -> Option<Command> {
+    match args {
+        [flag, identity] if flag == "--nonce" => Some(Command::Nonce(identity.clone())),
+        [flag] if flag == "--deploy" => Some(Command::Deploy(None)),
+        [flag, env_file] if flag == "--deploy" => Some(Command::Deploy(Some(env_file.clone()))),
+        [bundle] if !bundle.starts_with("--") => Some(Command::Relay(bundle.clone())),
+        _ => None,
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    tracing_subscriber::fmt::init();
+    init();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let Some(command) = command(&args) else {
+        eprintln!("relay: invalid arguments {:?}", args);
+        usage();
+        std::process::exit(2);
+    };
+    let env = relay_environment();
+    println!("rpc_url: {}", env.rpc_url.split("v2").next().unwrap());
+    println!("private_key: {}", scram(env.private_key.clone()));
+
+    let config = match registry_config() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("relay: {err}");
+            std::process::exit(1);
+        }
+    };
+    match command {
+        Command::Deploy(env_file) => {
+            // deploy the bundled registry for chains without the well known deployment
+            let contract = DidEthRegistry::deploy(env.rpc_url, env.private_key, config)
+                .await
+                .unwrap();
+            let registry = format!("{:#x}", contract.registry_address());
+            println!("deployer: {}", contract.wallet_address());
+            println!("registry: {}", registry);
+            let env_file = env_file.unwrap_or(".env".to_string());
+            write_env_var(Path::new(&env_file), "REGISTRY_ADDRESS", &registry).unwrap();
+            println!("REGISTRY_ADDRESS written to {}", env_file);
+        }
+        Command::Nonce(identity) => {
+            let contract = DidEthRegistry::connect(env.rpc_url, env.private_key, config)
+                .await
+                .unwrap();
+            let did = contract.did(&identity).unwrap();
+            let nonce = contract.nonce(&did).await.unwrap();
+            println!("nonce: {}", nonce);
+        }
+        Command::Relay(bundle_path) => {
+            let bundle = match read_bundle(Path::new(&bundle_path)) {
+                Ok(bundle) => bundle,
+                Err(err) => {
+                    eprintln!("relay: {bundle_path}: {err}");
+                    std::process::exit(1);
+                }
+            };
+            let contract = DidEthRegistry::connect(env.rpc_url, env.private_key, config)
+                .await
+                .unwrap();
+            println!("relayer: {}", contract.wallet_address());
+            for signed in bundle.iter() {
+                println!(
+                    "relay {} - identity: {:#x}, nonce: {}",
+                    signed.operation.function_name(),
+                    signed.identity,
+                    signed.nonce
+                );
+                let tx_receipt = contract.submit_signed(signed).await.unwrap();
+                println!("tx: {}", serde_json::to_string_pretty(&tx_receipt).unwrap());
+            }
+        }
+    }
+}
@@ -1,20 +1,60 @@
-use dideth::DidEthRegistry;
-use ethenv::{environment, init, printenv};
+use dideth::{DidEthRegistry, Operation};
+use ethenv::{
+    environment, init, printenv, registry_config, scram, sign_attributes, signing_environment,
+    write_bundle,
+};
+use std::{error::Error, path::Path};
+
+/// sign the revocations offline and write a meta transaction bundle for `relay`
+fn sign(bundle_path: &str) -> Result<(), Box<dyn Error>> {
+    let env = signing_environment();
+    println!("private_key: {}", scram(env.private_key.clone()));
+    println!("nonce: {}", env.nonce);
+
+    let config = registry_config()?;
+    let bundle = sign_attributes(&env, &config, |name, value| {
+        println!("sign revoke_attribute - key: {}, value: {}", name, value);
+        Operation::RevokeAttribute {
+            name: name.to_string(),
+            value: value.as_bytes().to_vec().into(),
+        }
+    })?;
+    write_bundle(Path::new(bundle_path), &bundle)?;
+    println!("bundle: {}", bundle_path);
+    Ok(())
+}
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
     tracing_subscriber::fmt::init();
     init();
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.len() == 3 && args[1] == "--sign" {
+        if let Err(err) = sign(&args[2]) {
+            eprintln!("revoke: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
     let env = environment();
     printenv(&env);
 
     let public_key = env.public_key.clone();
-    let contract = DidEthRegistry::new(env.rpc_url, env.private_key)
+    let config = match registry_config() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("revoke: {err}");
+            std::process::exit(1);
+        }
+    };
+    let contract = DidEthRegistry::connect(env.rpc_url, env.private_key, config)
         .await
         .unwrap();
     let sender = contract.wallet_address();
     println!("sender: {}", sender);
-    let owner_id = contract.owner(public_key.to_string()).await.unwrap();
+    let did = contract.did(&public_key).unwrap();
+    println!("did: {}", did);
+    let owner_id = contract.owner(&did).await.unwrap();
     println!("owner: {}", owner_id);
     for (key, value) in env.attribute.iter() {
         println!("revoke_attribute - key: {}, value: {}", key, value);
@@ -22,6 +62,6 @@ async fn main() {
             .revoke_attribute(key.clone(), value.clone())
             .await
             .unwrap();
-        println!("tx: {:?}", tx_receipt);
+        println!("tx: {}", serde_json::to_string_pretty(&tx_receipt).unwrap());
     }
 }